@@ -3,7 +3,11 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use counter::msg::{CountResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
+use counter::msg::{
+    ConfigResponse, CountResponse, CounterResponse, ExecuteMsg, HistoryResponse, InstantiateMsg,
+    LeaderboardResponse, ListCountersResponse, OwnershipResponse, ProfilesResponse, QueryMsg,
+    ReceiveMsg, RolesResponse, UserStatsResponse,
+};
 use counter::state::State;
 
 fn main() {
@@ -15,6 +19,16 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(ReceiveMsg), &out_dir);
     export_schema(&schema_for!(State), &out_dir);
     export_schema(&schema_for!(CountResponse), &out_dir);
+    export_schema(&schema_for!(ConfigResponse), &out_dir);
+    export_schema(&schema_for!(CounterResponse), &out_dir);
+    export_schema(&schema_for!(ListCountersResponse), &out_dir);
+    export_schema(&schema_for!(UserStatsResponse), &out_dir);
+    export_schema(&schema_for!(LeaderboardResponse), &out_dir);
+    export_schema(&schema_for!(HistoryResponse), &out_dir);
+    export_schema(&schema_for!(OwnershipResponse), &out_dir);
+    export_schema(&schema_for!(RolesResponse), &out_dir);
+    export_schema(&schema_for!(ProfilesResponse), &out_dir);
 }
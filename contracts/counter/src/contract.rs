@@ -1,14 +1,33 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128, Uint64, StdError,
+    coin, from_binary, to_binary, Addr, BankMsg, Binary, Coin, Deps, DepsMut, Env, MessageInfo,
+    Order, Response, StdError, StdResult, Storage, Timestamp, Uint128, Uint64, WasmMsg,
 };
+use cw_storage_plus::{Bound, IndexedMap, U128Key, U64Key};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use cw2::{set_contract_version, get_contract_version};
-use cw20_base::contract::query_token_info;
+use cw20::{Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, TokenInfoResponse};
 
 use crate::error::ContractError;
-use crate::msg::{CountResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
-use crate::state::{State, STATE, A, ITEM_A, B, ITEM_B};
+use crate::msg::{
+    ConfigResponse, ConfigUpdate, CountResponse, CounterResponse, ExecuteMsg, HistoryResponse,
+    InstantiateMsg, LeaderboardResponse, ListCountersResponse, MigrateMsg, OwnershipResponse,
+    Profile, ProfileEntry, ProfileKind, ProfilesResponse, QueryMsg, ReceiveMsg, RolesResponse,
+    UserStatsResponse,
+};
+use crate::state::{
+    a_profiles, b_profiles, user_stats, BoundMode, Config, CounterEntry, HistoryEntry,
+    ProfileIndexes, Role, State, Token, UserStats, A, B, CONFIG, COUNTERS, FEES, HISTORY,
+    HISTORY_BY_HEIGHT, HISTORY_SEQ, INCREMENT_ALLOWLIST, INCREMENT_FEE, ITEM_A, ITEM_B,
+    PENDING_OWNER, ROLES, STATE, TOKEN, TOKEN_FEES,
+};
+
+const MAX_NAME_LENGTH: usize = 64;
+const MAX_AGE: u64 = 150;
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:my-first-contract";
@@ -17,10 +36,24 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    let config = Config {
+        min: msg.min,
+        max: msg.max,
+        mode: msg.mode,
+    };
+    validate_config(&config, msg.count)?;
+    if let Some(a) = &msg.a {
+        validate_a(a)?;
+    }
+    if let Some(b) = &msg.b {
+        validate_b(b)?;
+    }
+    let token = token_config(deps.as_ref(), msg.token, msg.token_fee)?;
+    validate_increment_fee(&msg.increment_fee)?;
     let state = State {
         count: msg.count,
         owner: info.sender.clone(),
@@ -28,19 +61,23 @@ pub fn instantiate(
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     
     STATE.save(deps.storage, &state)?;
-    let a: A = A {
-        name: String::default(),
-        l_name: String::default(),
-        age: Uint64::default(),
-        num: Uint64::default(),
-    };
-    
-    let b: B = B {
-        name: String::default(),
-        age: Uint64::default(),
-    };
-    ITEM_A.save(deps.storage, &a)?;
-    ITEM_B.save(deps.storage, &b)?;
+    CONFIG.save(deps.storage, &config)?;
+    record_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "instantiate",
+        Uint128::zero(),
+        msg.count,
+    )?;
+    ITEM_A.save(deps.storage, &msg.a.unwrap_or_default())?;
+    ITEM_B.save(deps.storage, &msg.b.unwrap_or_default())?;
+    if let Some(token) = &token {
+        TOKEN.save(deps.storage, token)?;
+    }
+    if let Some(fee) = &msg.increment_fee {
+        INCREMENT_FEE.save(deps.storage, fee)?;
+    }
 
     Ok(Response::new()
         .add_attribute("method", "instantiate")
@@ -51,37 +88,799 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::Increment {} => try_increment(deps),
-        ExecuteMsg::Reset { count } => try_reset(deps, info, count),
+        ExecuteMsg::Increment {} => try_increment(deps, env, info, Uint128::new(1)),
+        ExecuteMsg::Decrement {} => try_decrement(deps, env, info, Uint128::new(1)),
+        ExecuteMsg::IncrementBy { amount } => try_increment(deps, env, info, amount),
+        ExecuteMsg::DecrementBy { amount } => try_decrement(deps, env, info, amount),
+        ExecuteMsg::Reset { count } => try_reset(deps, env, info, count),
+        ExecuteMsg::CreateCounter {
+            name,
+            count,
+            min,
+            max,
+            mode,
+        } => try_create_counter(deps, info, name, count, Config { min, max, mode }),
+        ExecuteMsg::IncrementCounter { name } => try_increment_counter(deps, env, info, name),
+        ExecuteMsg::ResetCounter { name, count } => try_reset_counter(deps, info, name, count),
+        ExecuteMsg::SetA {
+            name,
+            l_name,
+            age,
+            num,
+        } => try_set_a(
+            deps,
+            info,
+            A {
+                name,
+                l_name,
+                age,
+                num,
+            },
+        ),
+        ExecuteMsg::SetB { name, age } => try_set_b(deps, info, B { name, age }),
+        ExecuteMsg::RegisterA {
+            name,
+            l_name,
+            age,
+            num,
+        } => try_register_a(
+            deps,
+            info,
+            A {
+                name,
+                l_name,
+                age,
+                num,
+            },
+        ),
+        ExecuteMsg::RegisterB { name, age } => try_register_b(deps, info, B { name, age }),
+        ExecuteMsg::TransferOwnership { new_owner } => {
+            try_transfer_ownership(deps, info, new_owner)
+        }
+        ExecuteMsg::AcceptOwnership {} => try_accept_ownership(deps, info),
+        ExecuteMsg::GrantRole { role, address } => try_grant_role(deps, info, role, address),
+        ExecuteMsg::RevokeRole { role, address } => try_revoke_role(deps, info, role, address),
+        ExecuteMsg::UpdateToken { address, fee } => try_update_token(deps, info, address, fee),
+        ExecuteMsg::Receive(msg) => try_receive(deps, env, info, msg),
+        ExecuteMsg::SetIncrementAllowlist { enabled } => {
+            try_set_increment_allowlist(deps, info, enabled)
+        }
+        ExecuteMsg::UpdateConfig(update) => try_update_config(deps, info, update),
+        ExecuteMsg::WithdrawFees { recipient } => try_withdraw_fees(deps, info, recipient),
+        ExecuteMsg::WithdrawTokenFees { recipient } => {
+            try_withdraw_token_fees(deps, info, recipient)
+        }
     }
 }
 
-pub fn try_increment(deps: DepsMut) -> Result<Response, ContractError> {
-    STATE.update(deps.storage, |mut state| -> Result<_, ContractError> {
-        state.count = state.count.checked_add(Uint128::from(1u128)).unwrap();
-        Ok(state)
-    })?;
+pub fn try_increment(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    // a zero increment would be free while still counting as a change
+    if amount.is_zero() {
+        return Err(ContractError::ZeroIncrement {});
+    }
+    // with a fee set increments only come in through the token
+    if let Some(fee) = TOKEN.may_load(deps.storage)?.and_then(|token| token.fee) {
+        return Err(ContractError::TokenFeeRequired { fee });
+    }
+    let (fee, refund) = split_increment_fee(deps.as_ref(), &info, amount)?;
+    let res = increment(deps.branch(), env, &info.sender, amount)?;
+    settle_increment_fee(deps.storage, &info.sender, fee, refund, res)
+}
+
+/// Books the fee charged for an increment and refunds the rest of the funds
+fn settle_increment_fee(
+    storage: &mut dyn Storage,
+    sender: &Addr,
+    fee: Option<Coin>,
+    refund: Vec<Coin>,
+    res: Response,
+) -> Result<Response, ContractError> {
+    if let Some(fee) = fee {
+        FEES.update(storage, &fee.denom, |fees| -> StdResult<_> {
+            Ok(fees.unwrap_or_default().checked_add(fee.amount)?)
+        })?;
+    }
+    if refund.is_empty() {
+        return Ok(res);
+    }
+    Ok(res.add_message(BankMsg::Send {
+        to_address: sender.to_string(),
+        amount: refund,
+    }))
+}
 
-    Ok(Response::new().add_attribute("method", "try_increment"))
+/// Splits the funds sent into the increment fee due and the rest to refund
+fn split_increment_fee(
+    deps: Deps,
+    info: &MessageInfo,
+    amount: Uint128,
+) -> Result<(Option<Coin>, Vec<Coin>), ContractError> {
+    let fee = match INCREMENT_FEE.may_load(deps.storage)? {
+        Some(fee) => fee,
+        None => return Ok((None, info.funds.clone())),
+    };
+    let due = coin(
+        fee.amount
+            .checked_mul(amount)
+            .map_err(StdError::from)?
+            .u128(),
+        &fee.denom,
+    );
+    let paid = info
+        .funds
+        .iter()
+        .filter(|c| c.denom == due.denom)
+        .map(|c| c.amount)
+        .sum::<Uint128>();
+    if paid < due.amount {
+        return Err(ContractError::InsufficientFee { fee: due });
+    }
+
+    let mut refund: Vec<Coin> = info
+        .funds
+        .iter()
+        .filter(|c| c.denom != due.denom && !c.amount.is_zero())
+        .cloned()
+        .collect();
+    if paid > due.amount {
+        refund.push(coin((paid - due.amount).u128(), &due.denom));
+    }
+    Ok((Some(due), refund))
 }
+
+fn increment(
+    deps: DepsMut,
+    env: Env,
+    sender: &Addr,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    assert_can_increment(deps.as_ref(), sender)?;
+    let config = load_config(deps.as_ref())?;
+    let mut state = STATE.load(deps.storage)?;
+    let old = state.count;
+    state.count = increase(&config, old, amount)?;
+    STATE.save(deps.storage, &state)?;
+    record_change(deps.storage, &env, sender, "increment", old, state.count)?;
+    record_increment(deps, &env, sender)?;
+
+    count_response("try_increment", state.count)
+}
+
+pub fn try_decrement(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    assert_role(deps.as_ref(), &info.sender, Role::Operator)?;
+    nonpayable(&info)?;
+    let config = load_config(deps.as_ref())?;
+    let mut state = STATE.load(deps.storage)?;
+    let old = state.count;
+    state.count = decrease(&config, old, amount)?;
+    STATE.save(deps.storage, &state)?;
+    record_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "decrement",
+        old,
+        state.count,
+    )?;
+
+    count_response("try_decrement", state.count)
+}
+
+// appends a change of the default counter to the history
+fn record_change(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    method: &str,
+    old: Uint128,
+    new: Uint128,
+) -> StdResult<()> {
+    let id = HISTORY_SEQ.may_load(storage)?.unwrap_or_default() + 1;
+    HISTORY_SEQ.save(storage, &id)?;
+    let entry = HistoryEntry {
+        id,
+        height: env.block.height,
+        time: env.block.time,
+        sender: sender.clone(),
+        method: method.to_string(),
+        old,
+        new,
+    };
+    HISTORY.save(storage, U64Key::new(id), &entry)?;
+    HISTORY_BY_HEIGHT.save(storage, U64Key::new(env.block.height), &id)
+}
+
+// counts one more increment for the address
+fn record_increment(deps: DepsMut, env: &Env, address: &Addr) -> StdResult<UserStats> {
+    user_stats().update(deps.storage, address, |stats| -> StdResult<_> {
+        let increments = match stats {
+            Some(stats) => stats.increments.checked_add(Uint128::new(1))?,
+            None => Uint128::new(1),
+        };
+        Ok(UserStats {
+            increments,
+            last_height: env.block.height,
+            last_time: env.block.time,
+        })
+    })
+}
+
+fn load_config(deps: Deps) -> StdResult<Config> {
+    Ok(CONFIG.may_load(deps.storage)?.unwrap_or_default())
+}
+
+fn validate_config(config: &Config, count: Uint128) -> Result<(), ContractError> {
+    if config.lower() > config.upper() {
+        return Err(ContractError::InvalidBounds {});
+    }
+    if !config.contains(count) {
+        return Err(ContractError::OutOfBounds { count });
+    }
+    Ok(())
+}
+
+/// Adds amount to a count within the bounds, applying the mode if it goes past max
+pub fn increase(
+    config: &Config,
+    count: Uint128,
+    amount: Uint128,
+) -> Result<Uint128, ContractError> {
+    match count.checked_add(amount) {
+        Ok(new) if new <= config.upper() => Ok(new),
+        _ => match config.mode {
+            BoundMode::Saturate => Ok(config.upper()),
+            BoundMode::Error => Err(ContractError::Overflow {}),
+            BoundMode::Wrap => Ok(wrap(config, count, amount, true)),
+        },
+    }
+}
+
+/// Subtracts amount from a count within the bounds, applying the mode if it goes
+/// below min
+pub fn decrease(
+    config: &Config,
+    count: Uint128,
+    amount: Uint128,
+) -> Result<Uint128, ContractError> {
+    match count.checked_sub(amount) {
+        Ok(new) if new >= config.lower() => Ok(new),
+        _ => match config.mode {
+            BoundMode::Saturate => Ok(config.lower()),
+            BoundMode::Error => Err(ContractError::Underflow {}),
+            BoundMode::Wrap => Ok(wrap(config, count, amount, false)),
+        },
+    }
+}
+
+// moves amount steps up or down the cycle min, .., max, min, ..
+fn wrap(config: &Config, count: Uint128, amount: Uint128, up: bool) -> Uint128 {
+    let lower = config.lower().u128();
+    let span = config.upper().u128() - lower;
+    let offset = count.u128() - lower;
+    let amount = amount.u128();
+    if span == u128::MAX {
+        let offset = match up {
+            true => offset.wrapping_add(amount),
+            false => offset.wrapping_sub(amount),
+        };
+        return Uint128::new(offset);
+    }
+    // values in the cycle, and the steps that matter once full turns are dropped
+    let len = span + 1;
+    let step = amount % len;
+    let offset = match up {
+        true if offset >= len - step => offset - (len - step),
+        true => offset + step,
+        false if offset >= step => offset - step,
+        false => offset + (len - step),
+    };
+    Uint128::new(lower + offset)
+}
+
+// reports the new count both as attribute and as data
+fn count_response(method: &str, count: Uint128) -> Result<Response, ContractError> {
+    Ok(Response::new()
+        .add_attribute("method", method)
+        .add_attribute("count", count)
+        .set_data(to_binary(&CountResponse { count })?))
+}
+
 pub fn try_reset(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     count: Uint128,
 ) -> Result<Response, ContractError> {
-    STATE.update(deps.storage, |mut state| -> Result<_, ContractError> {
-        if info.sender != state.owner {
-            return Err(ContractError::Unauthorized {});
-        }
-        state.count = count;
+    assert_role(deps.as_ref(), &info.sender, Role::Operator)?;
+    nonpayable(&info)?;
+    let config = load_config(deps.as_ref())?;
+    if !config.contains(count) {
+        return Err(ContractError::OutOfBounds { count });
+    }
+    let mut state = STATE.load(deps.storage)?;
+    let old = state.count;
+    state.count = count;
+    STATE.save(deps.storage, &state)?;
+    record_change(deps.storage, &env, &info.sender, "reset", old, count)?;
+    Ok(Response::new().add_attribute("method", "reset"))
+}
+
+pub fn try_update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    update: ConfigUpdate,
+) -> Result<Response, ContractError> {
+    assert_role(deps.as_ref(), &info.sender, Role::Admin)?;
+    let mut config = load_config(deps.as_ref())?;
+    config.min = updated("min", config.min, update.min, update.clear_min)?;
+    config.max = updated("max", config.max, update.max, update.clear_max)?;
+    config.mode = update.mode.unwrap_or(config.mode);
+    let increment_fee = updated(
+        "increment_fee",
+        INCREMENT_FEE.may_load(deps.storage)?,
+        update.increment_fee,
+        update.clear_increment_fee,
+    )?;
+    let state = STATE.load(deps.storage)?;
+    validate_config(&config, state.count)?;
+    validate_increment_fee(&increment_fee)?;
+    CONFIG.save(deps.storage, &config)?;
+    match &increment_fee {
+        Some(fee) => INCREMENT_FEE.save(deps.storage, fee)?,
+        None => INCREMENT_FEE.remove(deps.storage),
+    }
+    Ok(Response::new().add_attribute("method", "update_config"))
+}
+
+// the new value of an optional setting, the current one unless set or cleared
+fn updated<T>(
+    field: &str,
+    current: Option<T>,
+    new: Option<T>,
+    clear: bool,
+) -> Result<Option<T>, ContractError> {
+    match (new, clear) {
+        (Some(_), true) => Err(ContractError::ConflictingUpdate {
+            field: field.to_string(),
+        }),
+        (Some(new), false) => Ok(Some(new)),
+        (None, true) => Ok(None),
+        (None, false) => Ok(current),
+    }
+}
+
+fn validate_increment_fee(fee: &Option<Coin>) -> Result<(), ContractError> {
+    match fee {
+        Some(fee) if fee.amount.is_zero() => Err(ContractError::InvalidZeroFee {}),
+        _ => Ok(()),
+    }
+}
+
+pub fn try_withdraw_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let fees = collected_fees(deps.as_ref())?;
+    if fees.is_empty() {
+        return Err(ContractError::NoFees {});
+    }
+    for fee in fees.iter() {
+        FEES.remove(deps.storage, &fee.denom);
+    }
+    Ok(Response::new()
+        .add_attribute("method", "withdraw_fees")
+        .add_attribute("recipient", recipient.as_str())
+        .add_message(BankMsg::Send {
+            to_address: recipient.into(),
+            amount: fees,
+        }))
+}
+
+pub fn try_withdraw_token_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let fees = collected_token_fees(deps.as_ref())?;
+    if fees.is_empty() {
+        return Err(ContractError::NoFees {});
+    }
+    let mut res = Response::new()
+        .add_attribute("method", "withdraw_token_fees")
+        .add_attribute("recipient", recipient.as_str());
+    for fee in fees {
+        TOKEN_FEES.remove(deps.storage, &Addr::unchecked(&fee.address));
+        res = res.add_message(WasmMsg::Execute {
+            contract_addr: fee.address,
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount: fee.amount,
+            })?,
+            funds: vec![],
+        });
+    }
+    Ok(res)
+}
+
+fn collected_token_fees(deps: Deps) -> StdResult<Vec<Cw20Coin>> {
+    TOKEN_FEES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (address, amount) = item?;
+            let address = String::from_utf8(address).map_err(StdError::invalid_utf8)?;
+            Ok(Cw20Coin { address, amount })
+        })
+        .collect()
+}
+
+fn collected_fees(deps: Deps) -> StdResult<Vec<Coin>> {
+    FEES.range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (denom, amount) = item?;
+            let denom = String::from_utf8(denom).map_err(StdError::invalid_utf8)?;
+            Ok(Coin { denom, amount })
+        })
+        .collect()
+}
+
+pub fn try_set_a(deps: DepsMut, info: MessageInfo, a: A) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    validate_a(&a)?;
+    ITEM_A.save(deps.storage, &a)?;
+    Ok(Response::new()
+        .add_attribute("method", "set_a")
+        .add_attribute("name", a.name)
+        .add_attribute("l_name", a.l_name)
+        .add_attribute("age", a.age)
+        .add_attribute("num", a.num))
+}
+
+pub fn try_set_b(deps: DepsMut, info: MessageInfo, b: B) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    validate_b(&b)?;
+    ITEM_B.save(deps.storage, &b)?;
+    Ok(Response::new()
+        .add_attribute("method", "set_b")
+        .add_attribute("name", b.name)
+        .add_attribute("age", b.age))
+}
+
+pub fn try_register_a(deps: DepsMut, info: MessageInfo, a: A) -> Result<Response, ContractError> {
+    validate_a(&a)?;
+    a_profiles().save(deps.storage, &info.sender, &a)?;
+    Ok(Response::new()
+        .add_attribute("method", "register_a")
+        .add_attribute("address", info.sender)
+        .add_attribute("name", a.name)
+        .add_attribute("l_name", a.l_name)
+        .add_attribute("age", a.age)
+        .add_attribute("num", a.num))
+}
+
+pub fn try_register_b(deps: DepsMut, info: MessageInfo, b: B) -> Result<Response, ContractError> {
+    validate_b(&b)?;
+    b_profiles().save(deps.storage, &info.sender, &b)?;
+    Ok(Response::new()
+        .add_attribute("method", "register_b")
+        .add_attribute("address", info.sender)
+        .add_attribute("name", b.name)
+        .add_attribute("age", b.age))
+}
+
+fn nonpayable(info: &MessageInfo) -> Result<(), ContractError> {
+    if !info.funds.is_empty() {
+        return Err(ContractError::NonPayable {});
+    }
+    Ok(())
+}
+
+fn assert_owner(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if *sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<(), ContractError> {
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+        return Err(ContractError::InvalidName {
+            max: MAX_NAME_LENGTH,
+        });
+    }
+    Ok(())
+}
+
+fn validate_age(age: Uint64) -> Result<(), ContractError> {
+    if age.u64() > MAX_AGE {
+        return Err(ContractError::InvalidAge { max: MAX_AGE });
+    }
+    Ok(())
+}
+
+fn validate_a(a: &A) -> Result<(), ContractError> {
+    validate_name(&a.name)?;
+    validate_name(&a.l_name)?;
+    validate_age(a.age)
+}
+
+fn validate_b(b: &B) -> Result<(), ContractError> {
+    validate_name(&b.name)?;
+    validate_age(b.age)
+}
+
+pub fn try_transfer_ownership(
+    deps: DepsMut,
+    info: MessageInfo,
+    new_owner: String,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    let new_owner = deps.api.addr_validate(&new_owner)?;
+    PENDING_OWNER.save(deps.storage, &new_owner)?;
+    Ok(Response::new()
+        .add_attribute("method", "transfer_ownership")
+        .add_attribute("pending_owner", new_owner))
+}
+
+pub fn try_accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let pending = PENDING_OWNER
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingOwner {})?;
+    if info.sender != pending {
+        return Err(ContractError::Unauthorized {});
+    }
+    PENDING_OWNER.remove(deps.storage);
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.owner = pending;
         Ok(state)
     })?;
-    Ok(Response::new().add_attribute("method", "reset"))
+    Ok(Response::new()
+        .add_attribute("method", "accept_ownership")
+        .add_attribute("owner", info.sender))
+}
+
+pub fn try_grant_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    role: Role,
+    address: String,
+) -> Result<Response, ContractError> {
+    assert_can_manage(deps.as_ref(), &info.sender, role)?;
+    let addr = deps.api.addr_validate(&address)?;
+    ROLES.save(deps.storage, (&addr, role.as_str()), &true)?;
+    Ok(Response::new()
+        .add_attribute("method", "grant_role")
+        .add_attribute("role", role.as_str())
+        .add_attribute("address", addr))
+}
+
+pub fn try_revoke_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    role: Role,
+    address: String,
+) -> Result<Response, ContractError> {
+    assert_can_manage(deps.as_ref(), &info.sender, role)?;
+    let addr = deps.api.addr_validate(&address)?;
+    ROLES.remove(deps.storage, (&addr, role.as_str()));
+    Ok(Response::new()
+        .add_attribute("method", "revoke_role")
+        .add_attribute("role", role.as_str())
+        .add_attribute("address", addr))
+}
+
+pub fn try_set_increment_allowlist(
+    deps: DepsMut,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    assert_role(deps.as_ref(), &info.sender, Role::Admin)?;
+    INCREMENT_ALLOWLIST.save(deps.storage, &enabled)?;
+    Ok(Response::new()
+        .add_attribute("method", "set_increment_allowlist")
+        .add_attribute("enabled", enabled.to_string()))
+}
+
+pub fn try_update_token(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: Option<String>,
+    fee: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    assert_role(deps.as_ref(), &info.sender, Role::Admin)?;
+    let res = Response::new().add_attribute("method", "update_token");
+    match token_config(deps.as_ref(), address, fee)? {
+        Some(token) => {
+            TOKEN.save(deps.storage, &token)?;
+            Ok(res.add_attribute("token", token.address))
+        }
+        None => {
+            TOKEN.remove(deps.storage);
+            Ok(res)
+        }
+    }
+}
+
+fn token_config(
+    deps: Deps,
+    address: Option<String>,
+    fee: Option<Uint128>,
+) -> Result<Option<Token>, ContractError> {
+    match address {
+        Some(address) => Ok(Some(Token {
+            address: deps.api.addr_validate(&address)?,
+            fee,
+        })),
+        None if fee.is_some() => Err(ContractError::NoToken {}),
+        None => Ok(None),
+    }
+}
+
+/// Increments on behalf of the cw20 sender, keeping what it sent for the owner
+pub fn try_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let token = TOKEN
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoToken {})?;
+    if info.sender != token.address {
+        return Err(ContractError::Unauthorized {});
+    }
+    if let Some(fee) = token.fee {
+        if wrapper.amount != fee {
+            return Err(ContractError::InvalidTokenFee { fee });
+        }
+    }
+    let sender = deps.api.addr_validate(&wrapper.sender)?;
+    if !wrapper.amount.is_zero() {
+        TOKEN_FEES.update(deps.storage, &token.address, |fees| -> StdResult<_> {
+            Ok(fees.unwrap_or_default() + wrapper.amount)
+        })?;
+    }
+    match from_binary(&wrapper.msg)? {
+        ReceiveMsg::Increment {} => increment(deps, env, &sender, Uint128::new(1)),
+    }
+}
+
+fn has_role(deps: Deps, address: &Addr, role: Role) -> StdResult<bool> {
+    let state = STATE.load(deps.storage)?;
+    Ok(*address == state.owner || ROLES.has(deps.storage, (address, role.as_str())))
+}
+
+fn assert_role(deps: Deps, sender: &Addr, role: Role) -> Result<(), ContractError> {
+    if !has_role(deps, sender, role)? {
+        return Err(ContractError::MissingRole {
+            role: role.as_str().to_string(),
+        });
+    }
+    Ok(())
+}
+
+// only the owner hands out the admin role, admins the others
+fn assert_can_manage(deps: Deps, sender: &Addr, role: Role) -> Result<(), ContractError> {
+    if role == Role::Admin {
+        let state = STATE.load(deps.storage)?;
+        if *sender != state.owner {
+            return Err(ContractError::Unauthorized {});
+        }
+        return Ok(());
+    }
+    assert_role(deps, sender, Role::Admin)
+}
+
+fn assert_can_increment(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    if INCREMENT_ALLOWLIST
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+    {
+        assert_role(deps, sender, Role::Incrementer)?;
+    }
+    Ok(())
+}
+
+pub fn try_create_counter(
+    deps: DepsMut,
+    info: MessageInfo,
+    name: String,
+    count: Uint128,
+    config: Config,
+) -> Result<Response, ContractError> {
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+        return Err(ContractError::InvalidCounterName {
+            max: MAX_NAME_LENGTH,
+        });
+    }
+    if COUNTERS.has(deps.storage, &name) {
+        return Err(ContractError::CounterExists { name });
+    }
+    validate_config(&config, count)?;
+    let entry = CounterEntry {
+        owner: info.sender,
+        count,
+        config,
+    };
+    COUNTERS.save(deps.storage, &name, &entry)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "create_counter")
+        .add_attribute("name", name)
+        .add_attribute("owner", entry.owner)
+        .add_attribute("count", count))
+}
+
+/// Increments a named counter by one, charging the same fee as the default counter.
+/// The history only tracks the default counter, so it is left alone.
+pub fn try_increment_counter(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    name: String,
+) -> Result<Response, ContractError> {
+    if let Some(fee) = TOKEN.may_load(deps.storage)?.and_then(|token| token.fee) {
+        return Err(ContractError::TokenFeeRequired { fee });
+    }
+    assert_can_increment(deps.as_ref(), &info.sender)?;
+    let (fee, refund) = split_increment_fee(deps.as_ref(), &info, Uint128::new(1))?;
+    let mut entry = load_counter(deps.as_ref(), &name)?;
+    entry.count = increase(&entry.config, entry.count, Uint128::new(1))?;
+    COUNTERS.save(deps.storage, &name, &entry)?;
+    record_increment(deps.branch(), &env, &info.sender)?;
+
+    let res = count_response("increment_counter", entry.count)?.add_attribute("name", name);
+    settle_increment_fee(deps.storage, &info.sender, fee, refund, res)
+}
+
+pub fn try_reset_counter(
+    deps: DepsMut,
+    info: MessageInfo,
+    name: String,
+    count: Uint128,
+) -> Result<Response, ContractError> {
+    let mut entry = load_counter(deps.as_ref(), &name)?;
+    if info.sender != entry.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !entry.config.contains(count) {
+        return Err(ContractError::OutOfBounds { count });
+    }
+    entry.count = count;
+    COUNTERS.save(deps.storage, &name, &entry)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "reset_counter")
+        .add_attribute("name", name)
+        .add_attribute("count", count))
+}
+
+fn load_counter(deps: Deps, name: &str) -> Result<CounterEntry, ContractError> {
+    COUNTERS
+        .may_load(deps.storage, name)?
+        .ok_or_else(|| ContractError::UnknownCounter {
+            name: name.to_string(),
+        })
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -90,21 +889,80 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::TokenInfo {} => to_binary(&query_token_info(deps)?),
         QueryMsg::GetCount {} => to_binary(&query_count(deps)?),
         QueryMsg::GetContractVersion {} => to_binary(&get_contract_version(deps.storage)?),
-        QueryMsg::GetA {} => to_binary(&get_a(deps)?),
-        QueryMsg::GetB {} => to_binary(&get_b(deps)?)
+        QueryMsg::GetA { address } => to_binary(&get_a(deps, address)?),
+        QueryMsg::GetB { address } => to_binary(&get_b(deps, address)?),
+        QueryMsg::ListProfiles {
+            kind,
+            start_after,
+            limit,
+        } => to_binary(&match kind {
+            ProfileKind::A => list_profiles(deps, &a_profiles(), Profile::A, start_after, limit),
+            ProfileKind::B => list_profiles(deps, &b_profiles(), Profile::B, start_after, limit),
+        }?),
+        QueryMsg::ProfilesByName {
+            kind,
+            name,
+            start_after,
+            limit,
+        } => to_binary(&match kind {
+            ProfileKind::A => {
+                profiles_by_name(deps, &a_profiles(), Profile::A, name, start_after, limit)
+            }
+            ProfileKind::B => {
+                profiles_by_name(deps, &b_profiles(), Profile::B, name, start_after, limit)
+            }
+        }?),
+        QueryMsg::ProfilesByAgeRange {
+            kind,
+            min_age,
+            max_age,
+            start_after,
+            limit,
+        } => {
+            let ages = (min_age, max_age);
+            to_binary(&match kind {
+                ProfileKind::A => {
+                    profiles_by_age(deps, &a_profiles(), Profile::A, ages, start_after, limit)
+                }
+                ProfileKind::B => {
+                    profiles_by_age(deps, &b_profiles(), Profile::B, ages, start_after, limit)
+                }
+            }?)
+        }
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::Ownership {} => to_binary(&query_ownership(deps)?),
+        QueryMsg::Roles { address } => to_binary(&query_roles(deps, address)?),
+        QueryMsg::GetCounter { name } => to_binary(&query_counter(deps, name)?),
+        QueryMsg::GetCountAtHeight { height } => to_binary(&query_count_at_height(deps, height)?),
+        QueryMsg::History { start_after, limit } => {
+            to_binary(&query_history(deps, start_after, limit)?)
+        }
+        QueryMsg::GetUserStats { address } => to_binary(&query_user_stats(deps, address)?),
+        QueryMsg::Leaderboard { start_after, limit } => {
+            to_binary(&query_leaderboard(deps, start_after, limit)?)
+        }
+        QueryMsg::ListCounters { start_after, limit } => {
+            to_binary(&query_list_counters(deps, start_after, limit)?)
+        }
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> StdResult<Response> {
     let ver = cw2::get_contract_version(deps.storage)?;
     // ensure we are migrating from an allowed contract
-    if ver.contract != CONTRACT_NAME.to_string() {
-        return Err(StdError::generic_err("Can only upgrade from same type").into());
+    if ver.contract != CONTRACT_NAME {
+        return Err(StdError::generic_err("Can only upgrade from same type"));
     }
     // note: better to do proper semver compare, but string compare *usually* works
-    if ver.version >= CONTRACT_VERSION.to_string() {
-        return Err(StdError::generic_err("Cannot upgrade from a newer version").into());
+    if ver.version.as_str() >= CONTRACT_VERSION {
+        return Err(StdError::generic_err("Cannot upgrade from a newer version"));
+    }
+    // releases before the history start it from the current count
+    if HISTORY_SEQ.may_load(deps.storage)?.is_none() {
+        let count = STATE.load(deps.storage)?.count;
+        let contract = env.contract.address.clone();
+        record_change(deps.storage, &env, &contract, "migrate", count, count)?;
     }
 
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
@@ -116,12 +974,290 @@ fn query_count(deps: Deps) -> StdResult<CountResponse> {
     Ok(CountResponse { count: state.count })
 }
 
-fn get_a(deps: Deps) -> StdResult<A> {
-    let state = ITEM_A.load(deps.storage)?;
-    Ok(state)
+// the last change before the block is the one in effect at its beginning
+fn query_count_at_height(deps: Deps, height: u64) -> StdResult<CountResponse> {
+    let end = Some(Bound::exclusive(U64Key::new(height)));
+    let id = HISTORY_BY_HEIGHT
+        .range(deps.storage, None, end, Order::Descending)
+        .next()
+        .transpose()?
+        .map(|(_, id)| id)
+        .ok_or_else(|| StdError::not_found("count at height"))?;
+    let entry = HISTORY.load(deps.storage, U64Key::new(id))?;
+    Ok(CountResponse { count: entry.new })
+}
+
+fn query_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<HistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+
+    let entries: StdResult<Vec<_>> = HISTORY
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, entry)| entry))
+        .collect();
+
+    Ok(HistoryResponse { entries: entries? })
+}
+
+fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = load_config(deps)?;
+    let token = TOKEN.may_load(deps.storage)?;
+    Ok(ConfigResponse {
+        min: config.min,
+        max: config.max,
+        mode: config.mode,
+        increment_allowlist: INCREMENT_ALLOWLIST
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        token: token.as_ref().map(|token| token.address.to_string()),
+        token_fee: token.and_then(|token| token.fee),
+        increment_fee: INCREMENT_FEE.may_load(deps.storage)?,
+        collected_fees: collected_fees(deps)?,
+        collected_token_fees: collected_token_fees(deps)?,
+    })
+}
+
+fn query_token_info(deps: Deps) -> StdResult<TokenInfoResponse> {
+    let token = TOKEN
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::not_found("cw20 token"))?;
+    deps.querier
+        .query_wasm_smart(token.address, &Cw20QueryMsg::TokenInfo {})
+}
+
+fn query_ownership(deps: Deps) -> StdResult<OwnershipResponse> {
+    let state = STATE.load(deps.storage)?;
+    let pending_owner = PENDING_OWNER.may_load(deps.storage)?;
+    Ok(OwnershipResponse {
+        owner: state.owner.into(),
+        pending_owner: pending_owner.map(String::from),
+    })
+}
+
+fn query_roles(deps: Deps, address: String) -> StdResult<RolesResponse> {
+    let addr = deps.api.addr_validate(&address)?;
+    let mut roles = vec![];
+    for role in Role::ALL.iter() {
+        if has_role(deps, &addr, *role)? {
+            roles.push(*role);
+        }
+    }
+    Ok(RolesResponse { roles })
+}
+
+fn counter_response(name: String, entry: CounterEntry) -> CounterResponse {
+    CounterResponse {
+        name,
+        owner: entry.owner.into(),
+        count: entry.count,
+        config: entry.config,
+    }
+}
+
+fn query_counter(deps: Deps, name: String) -> StdResult<CounterResponse> {
+    let entry = COUNTERS.load(deps.storage, &name)?;
+    Ok(counter_response(name, entry))
+}
+
+fn query_list_counters(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListCountersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let counters: StdResult<Vec<_>> = COUNTERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (name, entry) = item?;
+            let name = String::from_utf8(name).map_err(StdError::invalid_utf8)?;
+            Ok(counter_response(name, entry))
+        })
+        .collect();
+
+    Ok(ListCountersResponse {
+        counters: counters?,
+    })
+}
+
+fn user_stats_response(address: String, stats: UserStats) -> UserStatsResponse {
+    UserStatsResponse {
+        address,
+        increments: stats.increments,
+        last_height: stats.last_height,
+        last_time: stats.last_time,
+    }
+}
+
+fn query_user_stats(deps: Deps, address: String) -> StdResult<UserStatsResponse> {
+    let addr = deps.api.addr_validate(&address)?;
+    let stats = user_stats()
+        .may_load(deps.storage, &addr)?
+        .unwrap_or(UserStats {
+            increments: Uint128::zero(),
+            last_height: 0,
+            last_time: Timestamp::from_nanos(0),
+        });
+    Ok(user_stats_response(address, stats))
+}
+
+fn query_leaderboard(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<LeaderboardResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let stats = user_stats();
+    // resume below the index entry of the last address of the previous page
+    let end = match start_after {
+        Some(address) => {
+            let addr = deps.api.addr_validate(&address)?;
+            let last = stats.load(deps.storage, &addr)?;
+            let key = (
+                U128Key::new(last.increments.u128()),
+                addr.as_bytes().to_vec(),
+            );
+            Some(Bound::exclusive(stats.idx.increments.index_key(key)))
+        }
+        None => None,
+    };
+
+    let entries: StdResult<Vec<_>> = stats
+        .idx
+        .increments
+        .range(deps.storage, None, end, Order::Descending)
+        .take(limit)
+        .map(|item| {
+            let (address, stats) = item?;
+            let address = String::from_utf8(address).map_err(StdError::invalid_utf8)?;
+            Ok(user_stats_response(address, stats))
+        })
+        .collect();
+
+    Ok(LeaderboardResponse { entries: entries? })
+}
+
+fn get_a(deps: Deps, address: Option<String>) -> StdResult<A> {
+    match address {
+        Some(address) => {
+            let addr = deps.api.addr_validate(&address)?;
+            a_profiles().load(deps.storage, &addr)
+        }
+        None => ITEM_A.load(deps.storage),
+    }
+}
+
+fn get_b(deps: Deps, address: Option<String>) -> StdResult<B> {
+    match address {
+        Some(address) => {
+            let addr = deps.api.addr_validate(&address)?;
+            b_profiles().load(deps.storage, &addr)
+        }
+        None => ITEM_B.load(deps.storage),
+    }
+}
+
+type Profiles<'a, T> = IndexedMap<'a, &'a Addr, T, ProfileIndexes<'a, T>>;
+
+fn profile_entries<T>(
+    items: impl Iterator<Item = StdResult<(Vec<u8>, T)>>,
+    wrap: fn(T) -> Profile,
+    limit: Option<u32>,
+) -> StdResult<ProfilesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let profiles: StdResult<Vec<_>> = items
+        .take(limit)
+        .map(|item| {
+            let (address, profile) = item?;
+            let address = String::from_utf8(address).map_err(StdError::invalid_utf8)?;
+            Ok(ProfileEntry {
+                address,
+                profile: wrap(profile),
+            })
+        })
+        .collect();
+    Ok(ProfilesResponse {
+        profiles: profiles?,
+    })
+}
+
+fn list_profiles<T>(
+    deps: Deps,
+    profiles: &Profiles<T>,
+    wrap: fn(T) -> Profile,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ProfilesResponse>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    let start = start_after.map(Bound::exclusive);
+    let items = profiles.range(deps.storage, start, None, Order::Ascending);
+    profile_entries(items, wrap, limit)
 }
 
-fn get_b(deps: Deps) -> StdResult<B> {
-    let state = ITEM_B.load(deps.storage)?;
-    Ok(state)
+fn profiles_by_name<T>(
+    deps: Deps,
+    profiles: &Profiles<T>,
+    wrap: fn(T) -> Profile,
+    name: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ProfilesResponse>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    let start = start_after.map(Bound::exclusive);
+    let items = profiles.idx.name.prefix(name.into_bytes()).range(
+        deps.storage,
+        start,
+        None,
+        Order::Ascending,
+    );
+    profile_entries(items, wrap, limit)
+}
+
+fn profiles_by_age<T>(
+    deps: Deps,
+    profiles: &Profiles<T>,
+    wrap: fn(T) -> Profile,
+    (min_age, max_age): (u64, u64),
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ProfilesResponse>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    let index = &profiles.idx.age;
+    // an empty address sorts before every address of the same age
+    let age_key = |age: u64, address: &[u8]| index.index_key((U64Key::new(age), address.to_vec()));
+    let from_min_age = Bound::inclusive(age_key(min_age, &[]));
+    // the cursor only moves the start past min_age, never before it
+    let start = match start_after {
+        Some(address) => {
+            let addr = deps.api.addr_validate(&address)?;
+            let age = match wrap(profiles.load(deps.storage, &addr)?) {
+                Profile::A(a) => a.age,
+                Profile::B(b) => b.age,
+            };
+            if age.u64() < min_age {
+                from_min_age
+            } else {
+                Bound::exclusive(age_key(age.u64(), addr.as_bytes()))
+            }
+        }
+        None => from_min_age,
+    };
+    let end = max_age
+        .checked_add(1)
+        .map(|age| Bound::exclusive(age_key(age, &[])));
+    let items = index.range(deps.storage, Some(start), end, Order::Ascending);
+    profile_entries(items, wrap, limit)
 }
\ No newline at end of file
@@ -1,13 +1,73 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Coin, StdError, Uint128};
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
     #[error("Unauthorized")]
     Unauthorized {},
+
+    #[error("Sender lacks the {role} role")]
+    MissingRole { role: String },
+
+    #[error("No ownership transfer pending")]
+    NoPendingOwner {},
+
+    #[error("Count would go above the maximum")]
+    Overflow {},
+
+    #[error("Count would go below the minimum")]
+    Underflow {},
+
+    #[error("Minimum cannot be above maximum")]
+    InvalidBounds {},
+
+    #[error("Count {count} is out of bounds")]
+    OutOfBounds { count: Uint128 },
+
+    #[error("Counter name must be 1 to {max} characters")]
+    InvalidCounterName { max: usize },
+
+    #[error("Counter {name} already exists")]
+    CounterExists { name: String },
+
+    #[error("No counter {name}")]
+    UnknownCounter { name: String },
+
+    #[error("Name must be 1 to {max} characters")]
+    InvalidName { max: usize },
+
+    #[error("Age cannot be above {max}")]
+    InvalidAge { max: u64 },
+
+    #[error("No cw20 token linked")]
+    NoToken {},
+
+    #[error("Increments cost a fee of {fee} sent through the cw20 token")]
+    TokenFeeRequired { fee: Uint128 },
+
+    #[error("Send exactly the fee of {fee}")]
+    InvalidTokenFee { fee: Uint128 },
+
+    #[error("Increment fee cannot be zero")]
+    InvalidZeroFee {},
+
+    #[error("Cannot increment by zero")]
+    ZeroIncrement {},
+
+    #[error("Increment costs {fee}")]
+    InsufficientFee { fee: Coin },
+
+    #[error("No fees to withdraw")]
+    NoFees {},
+
+    #[error("This message does not accept funds")]
+    NonPayable {},
+
+    #[error("Cannot set and clear {field} at once")]
+    ConflictingUpdate { field: String },
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
@@ -1,17 +1,152 @@
-use cosmwasm_std::Uint128;
+use cosmwasm_std::{Coin, Timestamp, Uint128, Uint64};
+use cw20::{Cw20Coin, Cw20ReceiveMsg};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::state::{BoundMode, Config, HistoryEntry, Role, A, B};
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub count: Uint128,
+    // the count stays within min..=max, both inclusive
+    #[serde(default)]
+    pub min: Option<Uint128>,
+    #[serde(default)]
+    pub max: Option<Uint128>,
+    #[serde(default)]
+    pub mode: BoundMode,
+    // initial records of the contract, blank when unset
+    #[serde(default)]
+    pub a: Option<A>,
+    #[serde(default)]
+    pub b: Option<B>,
+    // cw20 token answering TokenInfo, with the fee to send it for an increment
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default)]
+    pub token_fee: Option<Uint128>,
+    // native fee for each unit an increment adds
+    #[serde(default)]
+    pub increment_fee: Option<Coin>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
     Increment {},
-    Reset { count: Uint128 },
+    Decrement {},
+    IncrementBy {
+        amount: Uint128,
+    },
+    DecrementBy {
+        amount: Uint128,
+    },
+    Reset {
+        count: Uint128,
+    },
+    // named counters, owned by their creator
+    CreateCounter {
+        name: String,
+        count: Uint128,
+        #[serde(default)]
+        min: Option<Uint128>,
+        #[serde(default)]
+        max: Option<Uint128>,
+        #[serde(default)]
+        mode: BoundMode,
+    },
+    IncrementCounter {
+        name: String,
+    },
+    ResetCounter {
+        name: String,
+        count: Uint128,
+    },
+    // owner only, replaces the record of the contract. Names must be 1 to 64
+    // characters and ages at most 150
+    SetA {
+        name: String,
+        l_name: String,
+        age: Uint64,
+        num: Uint64,
+    },
+    SetB {
+        name: String,
+        age: Uint64,
+    },
+    // registers or replaces the profile of the sender, validated like SetA and SetB
+    RegisterA {
+        name: String,
+        l_name: String,
+        age: Uint64,
+        num: Uint64,
+    },
+    RegisterB {
+        name: String,
+        age: Uint64,
+    },
+    // owner only, the new owner takes over once it accepts
+    TransferOwnership {
+        new_owner: String,
+    },
+    AcceptOwnership {},
+    // admins manage the operator and incrementer roles, the owner all of them
+    GrantRole {
+        role: Role,
+        address: String,
+    },
+    RevokeRole {
+        role: Role,
+        address: String,
+    },
+    // admin only, unlinks the token when address is None
+    UpdateToken {
+        address: Option<String>,
+        fee: Option<Uint128>,
+    },
+    // cw20 hook of the linked token, the amount sent must match the fee if one is set
+    Receive(Cw20ReceiveMsg),
+    // admin only, while enabled only incrementers may increment
+    SetIncrementAllowlist {
+        enabled: bool,
+    },
+    // admin only, the current count must be within the new bounds
+    UpdateConfig(ConfigUpdate),
+    // owner only, sends all collected increment fees
+    WithdrawFees {
+        recipient: String,
+    },
+    // owner only, transfers all collected cw20 tokens
+    WithdrawTokenFees {
+        recipient: String,
+    },
+}
+
+// fields left out keep their value, the clear flags unset the bound or fee and
+// cannot be combined with a new value for it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct ConfigUpdate {
+    #[serde(default)]
+    pub min: Option<Uint128>,
+    #[serde(default)]
+    pub max: Option<Uint128>,
+    #[serde(default)]
+    pub mode: Option<BoundMode>,
+    #[serde(default)]
+    pub increment_fee: Option<Coin>,
+    #[serde(default)]
+    pub clear_min: bool,
+    #[serde(default)]
+    pub clear_max: bool,
+    #[serde(default)]
+    pub clear_increment_fee: bool,
+}
+
+// the msg of a cw20 Send to the counter
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    Increment {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -19,10 +154,74 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     // GetCount returns the current count as a json-encoded number
     GetCount {},
+    // forwarded to the linked cw20 token
     TokenInfo {},
-    GetA {},
-    GetB {},
+    // the profile registered by the address, or the record of the contract
+    // without one
+    GetA {
+        #[serde(default)]
+        address: Option<String>,
+    },
+    GetB {
+        #[serde(default)]
+        address: Option<String>,
+    },
+    // profiles of the kind ordered by address
+    ListProfiles {
+        kind: ProfileKind,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // profiles of the kind with exactly that name, ordered by address
+    ProfilesByName {
+        kind: ProfileKind,
+        name: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // profiles of the kind aged min_age..=max_age, youngest first. start_after is
+    // the last address of the previous page
+    ProfilesByAgeRange {
+        kind: ProfileKind,
+        min_age: u64,
+        max_age: u64,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     GetContractVersion {},
+    Config {},
+    Ownership {},
+    // the roles held by the address
+    Roles {
+        address: String,
+    },
+    GetCounter {
+        name: String,
+    },
+    GetUserStats {
+        address: String,
+    },
+    // the count of the default counter at the beginning of the block, as a
+    // CountResponse
+    GetCountAtHeight {
+        height: u64,
+    },
+    // changes of the default counter, oldest first
+    History {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    // addresses by increments, most first. start_after is the last address of the
+    // previous page
+    Leaderboard {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // named counters ordered by name
+    ListCounters {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -33,3 +232,84 @@ pub struct MigrateMsg {}
 pub struct CountResponse {
     pub count: Uint128,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub min: Option<Uint128>,
+    pub max: Option<Uint128>,
+    pub mode: BoundMode,
+    pub increment_allowlist: bool,
+    pub token: Option<String>,
+    pub token_fee: Option<Uint128>,
+    pub increment_fee: Option<Coin>,
+    // fees collected and not withdrawn yet
+    pub collected_fees: Vec<Coin>,
+    pub collected_token_fees: Vec<Cw20Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileKind {
+    A,
+    B,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Profile {
+    A(A),
+    B(B),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProfileEntry {
+    pub address: String,
+    pub profile: Profile,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProfilesResponse {
+    pub profiles: Vec<ProfileEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OwnershipResponse {
+    pub owner: String,
+    pub pending_owner: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RolesResponse {
+    pub roles: Vec<Role>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CounterResponse {
+    pub name: String,
+    pub owner: String,
+    pub count: Uint128,
+    pub config: Config,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ListCountersResponse {
+    pub counters: Vec<CounterResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserStatsResponse {
+    pub address: String,
+    pub increments: Uint128,
+    pub last_height: u64,
+    pub last_time: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeaderboardResponse {
+    pub entries: Vec<UserStatsResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HistoryResponse {
+    pub entries: Vec<HistoryEntry>,
+}
@@ -1,8 +1,9 @@
 use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Uint128, Uint64};
-use cw_storage_plus::Item;
+use cosmwasm_std::{Addr, Coin, Timestamp, Uint128, Uint64};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex, U128Key, U64Key};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
@@ -24,6 +25,202 @@ pub struct B {
     pub age: Uint64,
 }
 
-pub const STATE: Item<State> = Item::new("state");
+/// The records of the contract itself, set by the owner
 pub const ITEM_A: Item<A> = Item::new("a");
 pub const ITEM_B: Item<B> = Item::new("b");
+
+/// What a change past min or max does to the count
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BoundMode {
+    /// Stops at the bound
+    Saturate,
+    /// Rejects the change
+    #[default]
+    Error,
+    /// Continues from the other bound
+    Wrap,
+}
+
+/// The cw20 token the counter is linked to
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Token {
+    pub address: Addr,
+    /// When set increments must be paid by sending exactly this many tokens
+    pub fee: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct Config {
+    pub min: Option<Uint128>,
+    pub max: Option<Uint128>,
+    pub mode: BoundMode,
+}
+
+impl Config {
+    pub fn lower(&self) -> Uint128 {
+        self.min.unwrap_or_else(Uint128::zero)
+    }
+
+    pub fn upper(&self) -> Uint128 {
+        self.max.unwrap_or(Uint128::MAX)
+    }
+
+    pub fn contains(&self, count: Uint128) -> bool {
+        self.lower() <= count && count <= self.upper()
+    }
+}
+
+/// Permissions granted by the owner or an admin. The owner holds every role.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Grants and revokes the other roles, updates the config
+    Admin,
+    /// Decrements and resets the default counter
+    Operator,
+    /// Increments while the increment allowlist is on
+    Incrementer,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Admin, Role::Operator, Role::Incrementer];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Operator => "operator",
+            Role::Incrementer => "incrementer",
+        }
+    }
+}
+
+pub const STATE: Item<State> = Item::new("state");
+/// Set while an ownership transfer waits to be accepted
+pub const PENDING_OWNER: Item<Addr> = Item::new("pending_owner");
+/// Roles held, by address and role name
+pub const ROLES: Map<(&Addr, &str), bool> = Map::new("roles");
+/// When set only incrementers may increment
+pub const INCREMENT_ALLOWLIST: Item<bool> = Item::new("increment_allowlist");
+/// Native fee for each unit an increment adds, unset when increments are free
+pub const INCREMENT_FEE: Item<Coin> = Item::new("increment_fee");
+/// Fees collected and not withdrawn yet, by denom
+pub const FEES: Map<&str, Uint128> = Map::new("fees");
+/// Unset while no cw20 token is linked
+pub const TOKEN: Item<Token> = Item::new("token");
+/// cw20 tokens received and not withdrawn yet, by token contract
+pub const TOKEN_FEES: Map<&Addr, Uint128> = Map::new("token_fees");
+/// Unset for counters instantiated before bounds existed, which behave as the default
+pub const CONFIG: Item<Config> = Item::new("config");
+
+/// A counter next to the default one, with its own owner and bounds
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CounterEntry {
+    pub owner: Addr,
+    pub count: Uint128,
+    pub config: Config,
+}
+
+pub const COUNTERS: Map<&str, CounterEntry> = Map::new("counters");
+
+/// How often an address incremented, and when it last did
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserStats {
+    pub increments: Uint128,
+    pub last_height: u64,
+    pub last_time: Timestamp,
+}
+
+pub struct UserStatsIndexes<'a> {
+    pub increments: MultiIndex<'a, (U128Key, Vec<u8>), UserStats>,
+}
+
+impl<'a> IndexList<UserStats> for UserStatsIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<UserStats>> + '_> {
+        let v: Vec<&dyn Index<UserStats>> = vec![&self.increments];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Stats by address, indexed by increments for the leaderboard
+pub fn user_stats<'a>() -> IndexedMap<'a, &'a Addr, UserStats, UserStatsIndexes<'a>> {
+    let indexes = UserStatsIndexes {
+        increments: MultiIndex::new(
+            |s, pk| (U128Key::new(s.increments.u128()), pk),
+            "user_stats",
+            "user_stats__increments",
+        ),
+    };
+    IndexedMap::new("user_stats", indexes)
+}
+
+/// Lookups of A and B profiles by name and by age
+pub struct ProfileIndexes<'a, T> {
+    pub name: MultiIndex<'a, (Vec<u8>, Vec<u8>), T>,
+    pub age: MultiIndex<'a, (U64Key, Vec<u8>), T>,
+}
+
+impl<'a, T> IndexList<T> for ProfileIndexes<'a, T>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<T>> + '_> {
+        let v: Vec<&dyn Index<T>> = vec![&self.name, &self.age];
+        Box::new(v.into_iter())
+    }
+}
+
+/// A profiles, each registered by its address. Kept apart from the "a" namespace
+/// of ITEM_A so the record of the contract stays readable.
+pub fn a_profiles<'a>() -> IndexedMap<'a, &'a Addr, A, ProfileIndexes<'a, A>> {
+    let indexes = ProfileIndexes {
+        name: MultiIndex::new(
+            |a: &A, pk| (a.name.as_bytes().to_vec(), pk),
+            "a_profiles",
+            "a_profiles__name",
+        ),
+        age: MultiIndex::new(
+            |a: &A, pk| (U64Key::new(a.age.u64()), pk),
+            "a_profiles",
+            "a_profiles__age",
+        ),
+    };
+    IndexedMap::new("a_profiles", indexes)
+}
+
+/// B profiles, each registered by its address, apart from ITEM_B
+pub fn b_profiles<'a>() -> IndexedMap<'a, &'a Addr, B, ProfileIndexes<'a, B>> {
+    let indexes = ProfileIndexes {
+        name: MultiIndex::new(
+            |b: &B, pk| (b.name.as_bytes().to_vec(), pk),
+            "b_profiles",
+            "b_profiles__name",
+        ),
+        age: MultiIndex::new(
+            |b: &B, pk| (U64Key::new(b.age.u64()), pk),
+            "b_profiles",
+            "b_profiles__age",
+        ),
+    };
+    IndexedMap::new("b_profiles", indexes)
+}
+
+/// A change of the default counter
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub height: u64,
+    pub time: Timestamp,
+    pub sender: Addr,
+    /// The message that changed the count
+    pub method: String,
+    /// The count before and after, their difference is the delta
+    pub old: Uint128,
+    pub new: Uint128,
+}
+
+/// Append-only log of count changes, by id
+pub const HISTORY: Map<U64Key, HistoryEntry> = Map::new("history");
+pub const HISTORY_SEQ: Item<u64> = Item::new("history_seq");
+/// Id of the last change made in each block
+pub const HISTORY_BY_HEIGHT: Map<U64Key, u64> = Map::new("history_by_height");
@@ -0,0 +1,1465 @@
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockStorage};
+use cosmwasm_std::{
+    coin, coins, from_binary, from_slice, to_binary, Addr, BankMsg, ContractResult, CosmosMsg,
+    Deps, DepsMut, Empty, Env, OwnedDeps, Querier, QuerierResult, QueryRequest, SystemError,
+    SystemResult, Uint128, Uint64, WasmMsg, WasmQuery,
+};
+use cw2::set_contract_version;
+use cw20::{Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, TokenInfoResponse};
+
+use crate::contract::{execute, instantiate, migrate, query};
+use crate::msg::{
+    ConfigResponse, ConfigUpdate, CountResponse, CounterResponse, ExecuteMsg, HistoryResponse,
+    InstantiateMsg, LeaderboardResponse, ListCountersResponse, MigrateMsg, OwnershipResponse,
+    Profile, ProfileKind, ProfilesResponse, QueryMsg, ReceiveMsg, RolesResponse, UserStatsResponse,
+};
+use crate::state::{BoundMode, Role, State, A, B, ITEM_A, ITEM_B, STATE};
+use crate::ContractError;
+
+const OWNER: &str = "creator";
+
+fn do_instantiate(deps: DepsMut, count: u128) {
+    do_instantiate_bounded(deps, count, None, None, BoundMode::Error);
+}
+
+fn do_instantiate_bounded(
+    deps: DepsMut,
+    count: u128,
+    min: Option<u128>,
+    max: Option<u128>,
+    mode: BoundMode,
+) {
+    let msg = InstantiateMsg {
+        count: Uint128::new(count),
+        min: min.map(Uint128::new),
+        max: max.map(Uint128::new),
+        mode,
+        a: None,
+        b: None,
+        token: None,
+        token_fee: None,
+        increment_fee: None,
+    };
+    instantiate(deps, mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+}
+
+// executes as the owner, who holds every role
+fn step(deps: DepsMut, msg: ExecuteMsg) -> Result<Uint128, ContractError> {
+    let res = execute(deps, mock_env(), mock_info(OWNER, &[]), msg)?;
+    let data: CountResponse = from_binary(&res.data.unwrap()).unwrap();
+    Ok(data.count)
+}
+
+fn by(amount: u128) -> Uint128 {
+    Uint128::new(amount)
+}
+
+fn get_count(deps: Deps) -> Uint128 {
+    let res = query(deps, mock_env(), QueryMsg::GetCount {}).unwrap();
+    let value: CountResponse = from_binary(&res).unwrap();
+    value.count
+}
+
+#[test]
+fn proper_initialization() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), 17);
+    assert_eq!(get_count(deps.as_ref()), Uint128::new(17));
+}
+
+#[test]
+fn increment_and_decrement() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), 17);
+    let info = mock_info("anyone", &[]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::Increment {},
+    )
+    .unwrap();
+    let data: CountResponse = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(data.count, Uint128::new(18));
+    assert_eq!(res.attributes[1].value, "18");
+
+    let msg = ExecuteMsg::IncrementBy {
+        amount: Uint128::new(10),
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    // decrementing takes the operator role
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Decrement {}).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::MissingRole {
+            role: "operator".to_string()
+        }
+    );
+    let owner = mock_info(OWNER, &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner.clone(),
+        ExecuteMsg::Decrement {},
+    )
+    .unwrap();
+    let msg = ExecuteMsg::DecrementBy {
+        amount: Uint128::new(5),
+    };
+    let res = execute(deps.as_mut(), mock_env(), owner, msg).unwrap();
+    let data: CountResponse = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(data.count, Uint128::new(22));
+    assert_eq!(get_count(deps.as_ref()), Uint128::new(22));
+}
+
+#[test]
+fn overflow_and_underflow_are_errors() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), u128::MAX);
+    let info = mock_info("anyone", &[]);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::Increment {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Overflow {});
+
+    let reset = ExecuteMsg::Reset {
+        count: Uint128::new(2),
+    };
+    let owner = mock_info(OWNER, &[]);
+    execute(deps.as_mut(), mock_env(), owner.clone(), reset).unwrap();
+    let msg = ExecuteMsg::DecrementBy {
+        amount: Uint128::new(3),
+    };
+    let err = execute(deps.as_mut(), mock_env(), owner, msg).unwrap_err();
+    assert_eq!(err, ContractError::Underflow {});
+    assert_eq!(get_count(deps.as_ref()), Uint128::new(2));
+}
+
+#[test]
+fn reset() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), 17);
+
+    let msg = ExecuteMsg::Reset {
+        count: Uint128::new(5),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::MissingRole {
+            role: "operator".to_string()
+        }
+    );
+
+    execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+    assert_eq!(get_count(deps.as_ref()), Uint128::new(5));
+}
+
+#[test]
+fn bounds_apply_the_mode() {
+    // error mode rejects the change
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate_bounded(deps.as_mut(), 5, Some(2), Some(8), BoundMode::Error);
+    let err = step(deps.as_mut(), ExecuteMsg::IncrementBy { amount: by(4) }).unwrap_err();
+    assert_eq!(err, ContractError::Overflow {});
+    let err = step(deps.as_mut(), ExecuteMsg::DecrementBy { amount: by(4) }).unwrap_err();
+    assert_eq!(err, ContractError::Underflow {});
+    assert_eq!(
+        step(deps.as_mut(), ExecuteMsg::IncrementBy { amount: by(3) }),
+        Ok(by(8))
+    );
+
+    // saturate stops at the bound
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate_bounded(deps.as_mut(), 5, Some(2), Some(8), BoundMode::Saturate);
+    assert_eq!(
+        step(deps.as_mut(), ExecuteMsg::IncrementBy { amount: by(40) }),
+        Ok(by(8))
+    );
+    assert_eq!(
+        step(deps.as_mut(), ExecuteMsg::DecrementBy { amount: by(7) }),
+        Ok(by(2))
+    );
+
+    // wrap continues from the other bound, 2..=8 is a cycle of 7
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate_bounded(deps.as_mut(), 5, Some(2), Some(8), BoundMode::Wrap);
+    assert_eq!(
+        step(deps.as_mut(), ExecuteMsg::IncrementBy { amount: by(4) }),
+        Ok(by(2))
+    );
+    assert_eq!(step(deps.as_mut(), ExecuteMsg::Decrement {}), Ok(by(8)));
+    assert_eq!(
+        step(deps.as_mut(), ExecuteMsg::IncrementBy { amount: by(15) }),
+        Ok(by(2))
+    );
+    assert_eq!(
+        step(deps.as_mut(), ExecuteMsg::DecrementBy { amount: by(10) }),
+        Ok(by(6))
+    );
+
+    // without bounds wrap covers the whole range
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate_bounded(deps.as_mut(), 1, None, None, BoundMode::Wrap);
+    assert_eq!(
+        step(deps.as_mut(), ExecuteMsg::DecrementBy { amount: by(2) }),
+        Ok(Uint128::MAX)
+    );
+    assert_eq!(step(deps.as_mut(), ExecuteMsg::Increment {}), Ok(by(0)));
+}
+
+#[test]
+fn owner_updates_config() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), 5);
+    let msg = InstantiateMsg {
+        count: by(1),
+        min: Some(by(2)),
+        max: None,
+        mode: BoundMode::Error,
+        a: None,
+        b: None,
+        token: None,
+        token_fee: None,
+        increment_fee: None,
+    };
+    let err = instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::OutOfBounds { count: by(1) });
+
+    let update = |min: u128, max: u128| {
+        ExecuteMsg::UpdateConfig(ConfigUpdate {
+            min: Some(by(min)),
+            max: Some(by(max)),
+            mode: Some(BoundMode::Saturate),
+            ..ConfigUpdate::default()
+        })
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        update(0, 10),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::MissingRole {
+            role: "admin".to_string()
+        }
+    );
+    let owner = mock_info(OWNER, &[]);
+    let err = execute(deps.as_mut(), mock_env(), owner.clone(), update(10, 0)).unwrap_err();
+    assert_eq!(err, ContractError::InvalidBounds {});
+    let err = execute(deps.as_mut(), mock_env(), owner.clone(), update(6, 10)).unwrap_err();
+    assert_eq!(err, ContractError::OutOfBounds { count: by(5) });
+    execute(deps.as_mut(), mock_env(), owner.clone(), update(0, 10)).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.max, Some(by(10)));
+    assert_eq!(config.mode, BoundMode::Saturate);
+    assert_eq!(
+        step(deps.as_mut(), ExecuteMsg::IncrementBy { amount: by(9) }),
+        Ok(by(10))
+    );
+
+    // resets must stay within the bounds too
+    let reset = ExecuteMsg::Reset { count: by(11) };
+    let err = execute(deps.as_mut(), mock_env(), owner, reset).unwrap_err();
+    assert_eq!(err, ContractError::OutOfBounds { count: by(11) });
+}
+
+fn create_counter(name: &str, count: u128) -> ExecuteMsg {
+    ExecuteMsg::CreateCounter {
+        name: name.to_string(),
+        count: by(count),
+        min: None,
+        max: Some(by(10)),
+        mode: BoundMode::Error,
+    }
+}
+
+fn list_counters(deps: Deps, start_after: Option<&str>, limit: u32) -> Vec<String> {
+    let msg = QueryMsg::ListCounters {
+        start_after: start_after.map(String::from),
+        limit: Some(limit),
+    };
+    let res: ListCountersResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+    res.counters.into_iter().map(|c| c.name).collect()
+}
+
+#[test]
+fn named_counters_are_independent() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), 100);
+    let alice = mock_info("alice", &[]);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        alice.clone(),
+        create_counter("visits", 0),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        create_counter("clicks", 9),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        alice.clone(),
+        create_counter("apples", 3),
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        alice.clone(),
+        create_counter("visits", 1),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::CounterExists {
+            name: "visits".to_string()
+        }
+    );
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        alice.clone(),
+        create_counter("", 1),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidCounterName { max: 64 });
+
+    let increment = |name: &str| ExecuteMsg::IncrementCounter {
+        name: name.to_string(),
+    };
+    assert_eq!(step(deps.as_mut(), increment("visits")), Ok(by(1)));
+    assert_eq!(step(deps.as_mut(), increment("clicks")), Ok(by(10)));
+    // each counter keeps its own bounds
+    assert_eq!(
+        step(deps.as_mut(), increment("clicks")),
+        Err(ContractError::Overflow {})
+    );
+    assert_eq!(
+        step(deps.as_mut(), increment("nope")),
+        Err(ContractError::UnknownCounter {
+            name: "nope".to_string()
+        })
+    );
+
+    // only the owner of a counter resets it
+    let reset = ExecuteMsg::ResetCounter {
+        name: "clicks".to_string(),
+        count: by(2),
+    };
+    let err = execute(deps.as_mut(), mock_env(), alice, reset.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), reset).unwrap();
+
+    let msg = QueryMsg::GetCounter {
+        name: "clicks".to_string(),
+    };
+    let res: CounterResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.owner, "bob");
+    assert_eq!(res.count, by(2));
+
+    // the default counter is untouched
+    assert_eq!(get_count(deps.as_ref()), by(100));
+
+    assert_eq!(
+        list_counters(deps.as_ref(), None, 2),
+        vec!["apples", "clicks"]
+    );
+    assert_eq!(
+        list_counters(deps.as_ref(), Some("clicks"), 2),
+        vec!["visits"]
+    );
+}
+
+fn leaderboard(deps: Deps, start_after: Option<&str>, limit: u32) -> Vec<(String, u128)> {
+    let msg = QueryMsg::Leaderboard {
+        start_after: start_after.map(String::from),
+        limit: Some(limit),
+    };
+    let res: LeaderboardResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+    res.entries
+        .into_iter()
+        .map(|e| (e.address, e.increments.u128()))
+        .collect()
+}
+
+#[test]
+fn increments_are_tracked_per_address() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), 0);
+    let calls = [("carol", 1), ("alice", 3), ("bob", 2), ("dave", 2)];
+    for (address, times) in calls.iter() {
+        for _ in 0..*times {
+            let info = mock_info(address, &[]);
+            execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Increment {}).unwrap();
+        }
+    }
+    // IncrementBy counts as one call whatever the amount
+    let mut env = mock_env();
+    env.block.height += 5;
+    let msg = ExecuteMsg::IncrementBy { amount: by(10) };
+    execute(deps.as_mut(), env.clone(), mock_info("carol", &[]), msg).unwrap();
+    assert_eq!(get_count(deps.as_ref()), by(18));
+
+    let msg = QueryMsg::GetUserStats {
+        address: "carol".to_string(),
+    };
+    let res: UserStatsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.increments, by(2));
+    assert_eq!(res.last_height, env.block.height);
+    assert_eq!(res.last_time, env.block.time);
+    let msg = QueryMsg::GetUserStats {
+        address: "nobody".to_string(),
+    };
+    let res: UserStatsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.increments, by(0));
+
+    // most increments first, ties by address descending
+    assert_eq!(
+        leaderboard(deps.as_ref(), None, 2),
+        vec![("alice".to_string(), 3), ("dave".to_string(), 2)]
+    );
+    assert_eq!(
+        leaderboard(deps.as_ref(), Some("dave"), 5),
+        vec![("carol".to_string(), 2), ("bob".to_string(), 2)]
+    );
+}
+
+fn env_at_height(height: u64) -> Env {
+    let mut env = mock_env();
+    env.block.height = height;
+    env
+}
+
+fn count_at_height(deps: Deps, height: u64) -> Uint128 {
+    let msg = QueryMsg::GetCountAtHeight { height };
+    let res: CountResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+    res.count
+}
+
+#[test]
+fn history_records_every_change() {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg {
+        count: by(5),
+        min: None,
+        max: None,
+        mode: BoundMode::Error,
+        a: None,
+        b: None,
+        token: None,
+        token_fee: None,
+        increment_fee: None,
+    };
+    instantiate(
+        deps.as_mut(),
+        env_at_height(100),
+        mock_info(OWNER, &[]),
+        msg,
+    )
+    .unwrap();
+    let grant = ExecuteMsg::GrantRole {
+        role: Role::Operator,
+        address: "alice".to_string(),
+    };
+    execute(
+        deps.as_mut(),
+        env_at_height(100),
+        mock_info(OWNER, &[]),
+        grant,
+    )
+    .unwrap();
+    let alice = mock_info("alice", &[]);
+    execute(
+        deps.as_mut(),
+        env_at_height(101),
+        alice.clone(),
+        ExecuteMsg::Increment {},
+    )
+    .unwrap();
+    let msg = ExecuteMsg::IncrementBy { amount: by(4) };
+    execute(deps.as_mut(), env_at_height(101), alice.clone(), msg).unwrap();
+    let reset = ExecuteMsg::Reset { count: by(1) };
+    execute(
+        deps.as_mut(),
+        env_at_height(103),
+        mock_info(OWNER, &[]),
+        reset,
+    )
+    .unwrap();
+    let msg = ExecuteMsg::DecrementBy { amount: by(1) };
+    execute(deps.as_mut(), env_at_height(105), alice, msg).unwrap();
+
+    // the count at the beginning of each block
+    let err = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetCountAtHeight { height: 100 },
+    );
+    assert!(err.is_err());
+    assert_eq!(count_at_height(deps.as_ref(), 101), by(5));
+    assert_eq!(count_at_height(deps.as_ref(), 102), by(10));
+    assert_eq!(count_at_height(deps.as_ref(), 103), by(10));
+    assert_eq!(count_at_height(deps.as_ref(), 104), by(1));
+    assert_eq!(count_at_height(deps.as_ref(), 200), by(0));
+
+    let msg = QueryMsg::History {
+        start_after: Some(2),
+        limit: Some(2),
+    };
+    let res: HistoryResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    let reset = &res.entries[1];
+    assert_eq!(res.entries.len(), 2);
+    assert_eq!(reset.id, 4);
+    assert_eq!(reset.height, 103);
+    assert_eq!(reset.sender, Addr::unchecked(OWNER));
+    assert_eq!(reset.method, "reset");
+    assert_eq!((reset.old, reset.new), (by(10), by(1)));
+}
+
+fn roles_of(deps: Deps, address: &str) -> Vec<Role> {
+    let msg = QueryMsg::Roles {
+        address: address.to_string(),
+    };
+    let res: RolesResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+    res.roles
+}
+
+#[test]
+fn admins_manage_roles() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), 5);
+    let grant = |role: Role, address: &str| ExecuteMsg::GrantRole {
+        role,
+        address: address.to_string(),
+    };
+    assert_eq!(roles_of(deps.as_ref(), OWNER), Role::ALL.to_vec());
+
+    // only the owner makes admins, admins hand out the other roles
+    let alice = mock_info("alice", &[]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        alice.clone(),
+        grant(Role::Operator, "bob"),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::MissingRole {
+            role: "admin".to_string()
+        }
+    );
+    let owner = mock_info(OWNER, &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner.clone(),
+        grant(Role::Admin, "alice"),
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        alice.clone(),
+        grant(Role::Admin, "bob"),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        alice.clone(),
+        grant(Role::Operator, "bob"),
+    )
+    .unwrap();
+    assert_eq!(roles_of(deps.as_ref(), "alice"), vec![Role::Admin]);
+    assert_eq!(roles_of(deps.as_ref(), "bob"), vec![Role::Operator]);
+    let bob = mock_info("bob", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        bob.clone(),
+        ExecuteMsg::Decrement {},
+    )
+    .unwrap();
+
+    let revoke = ExecuteMsg::RevokeRole {
+        role: Role::Operator,
+        address: "bob".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), alice.clone(), revoke).unwrap();
+    assert_eq!(roles_of(deps.as_ref(), "bob"), vec![]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        bob.clone(),
+        ExecuteMsg::Decrement {},
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::MissingRole {
+            role: "operator".to_string()
+        }
+    );
+
+    // with the allowlist on only incrementers increment
+    let allowlist = ExecuteMsg::SetIncrementAllowlist { enabled: true };
+    execute(deps.as_mut(), mock_env(), alice.clone(), allowlist).unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        bob.clone(),
+        ExecuteMsg::Increment {},
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::MissingRole {
+            role: "incrementer".to_string()
+        }
+    );
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        alice,
+        grant(Role::Incrementer, "bob"),
+    )
+    .unwrap();
+    execute(deps.as_mut(), mock_env(), bob, ExecuteMsg::Increment {}).unwrap();
+    assert_eq!(get_count(deps.as_ref()), by(5));
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert!(config.increment_allowlist);
+}
+
+#[test]
+fn ownership_transfer_takes_two_steps() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), 5);
+    let accept = ExecuteMsg::AcceptOwnership {};
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        accept.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NoPendingOwner {});
+
+    let transfer = ExecuteMsg::TransferOwnership {
+        new_owner: "alice".to_string(),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        transfer.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), transfer).unwrap();
+    let ownership = |deps: Deps| -> OwnershipResponse {
+        from_binary(&query(deps, mock_env(), QueryMsg::Ownership {}).unwrap()).unwrap()
+    };
+    assert_eq!(
+        ownership(deps.as_ref()).pending_owner,
+        Some("alice".to_string())
+    );
+
+    // the old owner keeps control until the transfer is accepted
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        accept.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    assert_eq!(ownership(deps.as_ref()).owner, OWNER);
+    execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), accept).unwrap();
+    assert_eq!(
+        ownership(deps.as_ref()),
+        OwnershipResponse {
+            owner: "alice".to_string(),
+            pending_owner: None,
+        }
+    );
+    let reset = ExecuteMsg::Reset { count: by(1) };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        reset.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::MissingRole {
+            role: "operator".to_string()
+        }
+    );
+    execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), reset).unwrap();
+}
+
+fn register_a(name: &str, age: u64) -> ExecuteMsg {
+    ExecuteMsg::RegisterA {
+        name: name.to_string(),
+        l_name: "smith".to_string(),
+        age: Uint64::new(age),
+        num: Uint64::new(7),
+    }
+}
+
+fn profile_addresses(deps: Deps, msg: QueryMsg) -> Vec<String> {
+    let res: ProfilesResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+    res.profiles.into_iter().map(|p| p.address).collect()
+}
+
+// A profiles of alice, bob, carol, dave and erin, and a B profile of bob
+fn do_register_profiles(mut deps: DepsMut) {
+    do_instantiate(deps.branch(), 0);
+    let profiles = [
+        ("alice", "al", 30),
+        ("bob", "bo", 30),
+        ("carol", "al", 45),
+        ("dave", "al", 30),
+        ("erin", "ed", 8),
+    ];
+    for (address, name, age) in profiles.iter() {
+        let info = mock_info(address, &[]);
+        execute(deps.branch(), mock_env(), info, register_a(name, *age)).unwrap();
+    }
+    let register_b = ExecuteMsg::RegisterB {
+        name: "bea".to_string(),
+        age: Uint64::new(0),
+    };
+    execute(deps, mock_env(), mock_info("bob", &[]), register_b).unwrap();
+}
+
+#[test]
+fn initial_records_are_validated() {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg {
+        count: by(0),
+        min: None,
+        max: None,
+        mode: BoundMode::Error,
+        a: None,
+        b: Some(B {
+            name: "bo".to_string(),
+            age: Uint64::new(151),
+        }),
+        token: None,
+        token_fee: None,
+        increment_fee: None,
+    };
+    let owner = mock_info(OWNER, &[]);
+    let err = instantiate(deps.as_mut(), mock_env(), owner.clone(), msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::InvalidAge { max: 150 });
+    let msg = InstantiateMsg {
+        b: Some(B {
+            name: "bo".to_string(),
+            age: Uint64::new(40),
+        }),
+        ..msg
+    };
+    instantiate(deps.as_mut(), mock_env(), owner, msg).unwrap();
+    // the initial values are the records of the contract, not profiles
+    let get_b = QueryMsg::GetB { address: None };
+    let res = query(deps.as_ref(), mock_env(), get_b).unwrap();
+    assert_eq!(from_binary::<B>(&res).unwrap().age, Uint64::new(40));
+    let get_b = QueryMsg::GetB {
+        address: Some(OWNER.to_string()),
+    };
+    assert!(query(deps.as_ref(), mock_env(), get_b).is_err());
+}
+
+#[test]
+fn profiles_are_validated() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), 0);
+    let alice = mock_info("alice", &[]);
+    let err = execute(deps.as_mut(), mock_env(), alice.clone(), register_a("", 30)).unwrap_err();
+    assert_eq!(err, ContractError::InvalidName { max: 64 });
+    let msg = register_a(&"x".repeat(65), 30);
+    let err = execute(deps.as_mut(), mock_env(), alice.clone(), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidName { max: 64 });
+    let msg = register_a("al", 200);
+    let err = execute(deps.as_mut(), mock_env(), alice, msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidAge { max: 150 });
+}
+
+#[test]
+fn users_register_profiles() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), 0);
+    // setting it again replaces it
+    let alice = mock_info("alice", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        alice.clone(),
+        register_a("al", 20),
+    )
+    .unwrap();
+    execute(deps.as_mut(), mock_env(), alice, register_a("al", 30)).unwrap();
+    let get_a = QueryMsg::GetA {
+        address: Some("alice".to_string()),
+    };
+    let res = query(deps.as_ref(), mock_env(), get_a).unwrap();
+    assert_eq!(
+        from_binary::<A>(&res).unwrap(),
+        A {
+            name: "al".to_string(),
+            l_name: "smith".to_string(),
+            age: Uint64::new(30),
+            num: Uint64::new(7),
+        }
+    );
+}
+
+#[test]
+fn profiles_are_listed_by_address() {
+    let mut deps = mock_dependencies(&[]);
+    do_register_profiles(deps.as_mut());
+    let list = |kind: ProfileKind, start_after: Option<&str>| QueryMsg::ListProfiles {
+        kind,
+        start_after: start_after.map(String::from),
+        limit: Some(2),
+    };
+    assert_eq!(
+        profile_addresses(deps.as_ref(), list(ProfileKind::A, None)),
+        ["alice", "bob"]
+    );
+    assert_eq!(
+        profile_addresses(deps.as_ref(), list(ProfileKind::A, Some("dave"))),
+        ["erin"]
+    );
+    let res = query(deps.as_ref(), mock_env(), list(ProfileKind::B, None)).unwrap();
+    let res: ProfilesResponse = from_binary(&res).unwrap();
+    assert_eq!(res.profiles.len(), 1);
+    assert_eq!(
+        res.profiles[0].profile,
+        Profile::B(B {
+            name: "bea".to_string(),
+            age: Uint64::new(0),
+        })
+    );
+}
+
+#[test]
+fn profiles_are_found_by_name() {
+    let mut deps = mock_dependencies(&[]);
+    do_register_profiles(deps.as_mut());
+    let by_name = |start_after: Option<&str>| QueryMsg::ProfilesByName {
+        kind: ProfileKind::A,
+        name: "al".to_string(),
+        start_after: start_after.map(String::from),
+        limit: Some(2),
+    };
+    assert_eq!(
+        profile_addresses(deps.as_ref(), by_name(None)),
+        ["alice", "carol"]
+    );
+    assert_eq!(
+        profile_addresses(deps.as_ref(), by_name(Some("carol"))),
+        ["dave"]
+    );
+}
+
+#[test]
+fn profiles_are_found_by_age() {
+    let mut deps = mock_dependencies(&[]);
+    do_register_profiles(deps.as_mut());
+    // youngest first, the same age by address
+    let by_age = |start_after: Option<&str>| QueryMsg::ProfilesByAgeRange {
+        kind: ProfileKind::A,
+        min_age: 10,
+        max_age: 45,
+        start_after: start_after.map(String::from),
+        limit: Some(2),
+    };
+    assert_eq!(
+        profile_addresses(deps.as_ref(), by_age(None)),
+        ["alice", "bob"]
+    );
+    assert_eq!(
+        profile_addresses(deps.as_ref(), by_age(Some("bob"))),
+        ["dave", "carol"]
+    );
+    assert_eq!(
+        profile_addresses(deps.as_ref(), by_age(Some("carol"))),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn age_pages_start_no_younger_than_min_age() {
+    let mut deps = mock_dependencies(&[]);
+    do_register_profiles(deps.as_mut());
+    let by_age = |start_after: Option<&str>| QueryMsg::ProfilesByAgeRange {
+        kind: ProfileKind::A,
+        min_age: 31,
+        max_age: 45,
+        start_after: start_after.map(String::from),
+        limit: Some(2),
+    };
+    // erin is below the range, the page still starts at min_age
+    assert_eq!(
+        profile_addresses(deps.as_ref(), by_age(Some("erin"))),
+        ["carol"]
+    );
+    assert_eq!(
+        profile_addresses(deps.as_ref(), by_age(Some("alice"))),
+        ["carol"]
+    );
+    assert_eq!(
+        profile_addresses(deps.as_ref(), by_age(Some("carol"))),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn only_the_owner_sets_the_records() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), 0);
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::GetA { address: None }).unwrap();
+    assert_eq!(from_binary::<A>(&res).unwrap(), A::default());
+
+    let set_a = ExecuteMsg::SetA {
+        name: "al".to_string(),
+        l_name: "smith".to_string(),
+        age: Uint64::new(30),
+        num: Uint64::new(7),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        set_a.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let set_b = ExecuteMsg::SetB {
+        name: "bo".to_string(),
+        age: Uint64::new(151),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        set_b.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let err = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), set_b).unwrap_err();
+    assert_eq!(err, ContractError::InvalidAge { max: 150 });
+
+    execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), set_a).unwrap();
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::GetA { address: None }).unwrap();
+    assert_eq!(from_binary::<A>(&res).unwrap().name, "al");
+    // the owner's profile is unaffected
+    let get_a = QueryMsg::GetA {
+        address: Some(OWNER.to_string()),
+    };
+    assert!(query(deps.as_ref(), mock_env(), get_a).is_err());
+}
+
+const TOKEN: &str = "token";
+
+// answers TokenInfo for the token contract only
+struct TokenQuerier;
+
+impl Querier for TokenQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        match from_slice(bin_request).unwrap() {
+            QueryRequest::<Empty>::Wasm(WasmQuery::Smart { contract_addr, msg })
+                if contract_addr == TOKEN =>
+            {
+                assert_eq!(
+                    from_binary::<Cw20QueryMsg>(&msg).unwrap(),
+                    Cw20QueryMsg::TokenInfo {}
+                );
+                let info = TokenInfoResponse {
+                    name: "Counter Token".to_string(),
+                    symbol: "CNT".to_string(),
+                    decimals: 6,
+                    total_supply: by(1000),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_binary(&info).unwrap()))
+            }
+            _ => SystemResult::Err(SystemError::NoSuchContract {
+                addr: "unknown".to_string(),
+            }),
+        }
+    }
+}
+
+fn receive(sender: &str, amount: u128) -> ExecuteMsg {
+    ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: sender.to_string(),
+        amount: by(amount),
+        msg: to_binary(&ReceiveMsg::Increment {}).unwrap(),
+    })
+}
+
+#[test]
+fn records_survive_a_migration() {
+    let mut deps = mock_dependencies(&[]);
+    // the state of the first release, which only had the records
+    let state = State {
+        count: by(3),
+        owner: Addr::unchecked(OWNER),
+    };
+    STATE.save(deps.as_mut().storage, &state).unwrap();
+    let record = A {
+        name: "al".to_string(),
+        l_name: "smith".to_string(),
+        age: Uint64::new(30),
+        num: Uint64::new(7),
+    };
+    ITEM_A.save(deps.as_mut().storage, &record).unwrap();
+    ITEM_B.save(deps.as_mut().storage, &B::default()).unwrap();
+    set_contract_version(
+        deps.as_mut().storage,
+        "crates.io:my-first-contract",
+        "0.1.0",
+    )
+    .unwrap();
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    // the history starts from the count at the migration
+    let height = mock_env().block.height;
+    assert_eq!(count_at_height(deps.as_ref(), height + 1), by(3));
+
+    let info = mock_info(OWNER, &[]);
+    execute(deps.as_mut(), mock_env(), info, register_a("ed", 8)).unwrap();
+    let get_a: QueryMsg = from_slice(br#"{"get_a":{}}"#).unwrap();
+    let res = query(deps.as_ref(), mock_env(), get_a).unwrap();
+    assert_eq!(from_binary::<A>(&res).unwrap(), record);
+    let get_a = QueryMsg::GetA {
+        address: Some(OWNER.to_string()),
+    };
+    let res = query(deps.as_ref(), mock_env(), get_a).unwrap();
+    assert_eq!(from_binary::<A>(&res).unwrap().name, "ed");
+}
+
+#[test]
+fn increments_are_paid_in_the_token() {
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: TokenQuerier,
+    };
+    let msg = InstantiateMsg {
+        count: by(0),
+        min: None,
+        max: None,
+        mode: BoundMode::Error,
+        a: None,
+        b: None,
+        token: Some(TOKEN.to_string()),
+        token_fee: Some(by(5)),
+        increment_fee: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::TokenInfo {}).unwrap();
+    assert_eq!(
+        from_binary::<TokenInfoResponse>(&res).unwrap().symbol,
+        "CNT"
+    );
+
+    let alice = mock_info("alice", &[]);
+    let err = execute(deps.as_mut(), mock_env(), alice, ExecuteMsg::Increment {}).unwrap_err();
+    assert_eq!(err, ContractError::TokenFeeRequired { fee: by(5) });
+    // only the linked token calls the hook, with exactly the fee
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("fake", &[]),
+        receive("alice", 5),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let token = mock_info(TOKEN, &[]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        token.clone(),
+        receive("alice", 4),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidTokenFee { fee: by(5) });
+    execute(deps.as_mut(), mock_env(), token, receive("alice", 5)).unwrap();
+    assert_eq!(get_count(deps.as_ref()), by(1));
+    let msg = QueryMsg::GetUserStats {
+        address: "alice".to_string(),
+    };
+    let res: UserStatsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.increments, by(1));
+
+    // without a fee plain increments work again
+    let owner = mock_info(OWNER, &[]);
+    let update = |address: Option<&str>, fee: Option<u128>| ExecuteMsg::UpdateToken {
+        address: address.map(String::from),
+        fee: fee.map(Uint128::new),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner.clone(),
+        update(None, Some(5)),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NoToken {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner.clone(),
+        update(Some(TOKEN), None),
+    )
+    .unwrap();
+    assert_eq!(step(deps.as_mut(), ExecuteMsg::Increment {}), Ok(by(2)));
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        (config.token, config.token_fee),
+        (Some(TOKEN.to_string()), None)
+    );
+
+    execute(deps.as_mut(), mock_env(), owner, update(None, None)).unwrap();
+    assert!(query(deps.as_ref(), mock_env(), QueryMsg::TokenInfo {}).is_err());
+}
+
+#[test]
+fn token_fees_are_withdrawn_by_the_owner() {
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: TokenQuerier,
+    };
+    let msg = InstantiateMsg {
+        count: by(0),
+        min: None,
+        max: None,
+        mode: BoundMode::Error,
+        a: None,
+        b: None,
+        token: Some(TOKEN.to_string()),
+        token_fee: None,
+        increment_fee: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+    // without a fee any amount is accepted
+    let token = mock_info(TOKEN, &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        token.clone(),
+        receive("alice", 3),
+    )
+    .unwrap();
+    execute(deps.as_mut(), mock_env(), token, receive("bob", 4)).unwrap();
+    assert_eq!(get_count(deps.as_ref()), by(2));
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    let fees = vec![Cw20Coin {
+        address: TOKEN.to_string(),
+        amount: by(7),
+    }];
+    assert_eq!(config.collected_token_fees, fees);
+
+    let withdraw = ExecuteMsg::WithdrawTokenFees {
+        recipient: "treasury".to_string(),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        withdraw.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        withdraw.clone(),
+    )
+    .unwrap();
+    let transfer = Cw20ExecuteMsg::Transfer {
+        recipient: "treasury".to_string(),
+        amount: by(7),
+    };
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: TOKEN.to_string(),
+            msg: to_binary(&transfer).unwrap(),
+            funds: vec![],
+        })
+    );
+    let err = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), withdraw).unwrap_err();
+    assert_eq!(err, ContractError::NoFees {});
+}
+
+// increments cost 10ucosm per unit
+fn do_instantiate_with_fee(deps: DepsMut) {
+    let msg = InstantiateMsg {
+        count: by(0),
+        min: None,
+        max: None,
+        mode: BoundMode::Error,
+        a: None,
+        b: None,
+        token: None,
+        token_fee: None,
+        increment_fee: Some(coin(10, "ucosm")),
+    };
+    instantiate(deps, mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+}
+
+#[test]
+fn increment_fee_cannot_be_zero() {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg {
+        count: by(0),
+        min: None,
+        max: None,
+        mode: BoundMode::Error,
+        a: None,
+        b: None,
+        token: None,
+        token_fee: None,
+        increment_fee: Some(coin(0, "ucosm")),
+    };
+    let err = instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidZeroFee {});
+}
+
+#[test]
+fn increments_pay_the_native_fee() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate_with_fee(deps.as_mut());
+    // each unit added costs the fee
+    let owner = mock_info(OWNER, &[]);
+    let err = execute(deps.as_mut(), mock_env(), owner, ExecuteMsg::Increment {}).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InsufficientFee {
+            fee: coin(10, "ucosm")
+        }
+    );
+    let info = mock_info("alice", &coins(25, "ucosm"));
+    let msg = ExecuteMsg::IncrementBy { amount: by(3) };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InsufficientFee {
+            fee: coin(30, "ucosm")
+        }
+    );
+    let msg = ExecuteMsg::IncrementBy { amount: by(2) };
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(get_count(deps.as_ref()), by(2));
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "alice".to_string(),
+            amount: coins(5, "ucosm"),
+        })
+    );
+}
+
+#[test]
+fn zero_increments_are_rejected() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate_with_fee(deps.as_mut());
+    let msg = ExecuteMsg::IncrementBy { amount: by(0) };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::ZeroIncrement {});
+    assert_eq!(get_count(deps.as_ref()), by(0));
+}
+
+#[test]
+fn other_denoms_are_refunded_in_full() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate_with_fee(deps.as_mut());
+    let info = mock_info("bob", &[coin(10, "ucosm"), coin(7, "uatom")]);
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Increment {}).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "bob".to_string(),
+            amount: coins(7, "uatom"),
+        })
+    );
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.increment_fee, Some(coin(10, "ucosm")));
+    assert_eq!(config.collected_fees, coins(10, "ucosm"));
+}
+
+#[test]
+fn named_counter_increments_pay_the_native_fee() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate_with_fee(deps.as_mut());
+    let alice = mock_info("alice", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        alice.clone(),
+        create_counter("visits", 0),
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::IncrementCounter {
+        name: "visits".to_string(),
+    };
+    let err = execute(deps.as_mut(), mock_env(), alice, msg.clone()).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InsufficientFee {
+            fee: coin(10, "ucosm")
+        }
+    );
+    let info = mock_info("alice", &coins(12, "ucosm"));
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "alice".to_string(),
+            amount: coins(2, "ucosm"),
+        })
+    );
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.collected_fees, coins(10, "ucosm"));
+}
+
+#[test]
+fn owner_withdraws_the_native_fees() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate_with_fee(deps.as_mut());
+    let info = mock_info("alice", &coins(30, "ucosm"));
+    let msg = ExecuteMsg::IncrementBy { amount: by(3) };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let withdraw = ExecuteMsg::WithdrawFees {
+        recipient: "treasury".to_string(),
+    };
+    let info = mock_info("alice", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, withdraw.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let owner = mock_info(OWNER, &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner.clone(), withdraw.clone()).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: coins(30, "ucosm"),
+        })
+    );
+    let err = execute(deps.as_mut(), mock_env(), owner, withdraw).unwrap_err();
+    assert_eq!(err, ContractError::NoFees {});
+}
+
+#[test]
+fn config_updates_keep_what_is_left_out() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate_bounded(deps.as_mut(), 5, Some(2), Some(10), BoundMode::Wrap);
+    let owner = mock_info(OWNER, &[]);
+    let update = ConfigUpdate {
+        increment_fee: Some(coin(10, "ucosm")),
+        ..ConfigUpdate::default()
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner.clone(),
+        ExecuteMsg::UpdateConfig(update),
+    )
+    .unwrap();
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!((config.min, config.max), (Some(by(2)), Some(by(10))));
+    assert_eq!(config.mode, BoundMode::Wrap);
+    assert_eq!(config.increment_fee, Some(coin(10, "ucosm")));
+
+    let update = ConfigUpdate {
+        increment_fee: Some(coin(20, "ucosm")),
+        clear_increment_fee: true,
+        ..ConfigUpdate::default()
+    };
+    let msg = ExecuteMsg::UpdateConfig(update);
+    let err = execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ConflictingUpdate {
+            field: "increment_fee".to_string()
+        }
+    );
+    let update = ConfigUpdate {
+        clear_max: true,
+        clear_increment_fee: true,
+        ..ConfigUpdate::default()
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner,
+        ExecuteMsg::UpdateConfig(update),
+    )
+    .unwrap();
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!((config.min, config.max), (Some(by(2)), None));
+    assert_eq!(config.increment_fee, None);
+}
+
+#[test]
+fn decrement_and_reset_refuse_funds() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), 5);
+    let info = mock_info(OWNER, &coins(10, "ucosm"));
+    let msgs = [
+        ExecuteMsg::Decrement {},
+        ExecuteMsg::DecrementBy { amount: by(2) },
+        ExecuteMsg::Reset { count: by(0) },
+    ];
+    for msg in msgs.iter() {
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::NonPayable {});
+    }
+    assert_eq!(get_count(deps.as_ref()), by(5));
+}
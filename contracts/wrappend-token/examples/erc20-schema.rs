@@ -4,7 +4,16 @@ use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use cw20::{AllowanceResponse, BalanceResponse, TokenInfoResponse};
-use wrapped_token::erc20::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use cw_controllers::{AdminResponse, ClaimsResponse};
+use wrapped_token::erc20::{
+    AirdropClaimedResponse, ChannelResponse, ComplianceResponse, DelegationsResponse,
+    EscrowResponse, EscrowsResponse, ExecuteMsg, FailedWithdrawalsResponse, FlashLoanFeeResponse,
+    FrozenAccountsResponse, HooksResponse, InstantiateMsg, ListChannelsResponse,
+    MerkleStageResponse, PermitNonceResponse, QueryMsg, StakingResponse, StreamResponse,
+    TotalSupplyResponse, VestingResponse,
+};
+use wrapped_token::hooks::BalanceChangedHookMsg;
+use wrapped_token::permit::PermitMsg;
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -18,4 +27,25 @@ fn main() {
     export_schema(&schema_for!(AllowanceResponse), &out_dir);
     export_schema(&schema_for!(BalanceResponse), &out_dir);
     export_schema(&schema_for!(TokenInfoResponse), &out_dir);
+    export_schema(&schema_for!(TotalSupplyResponse), &out_dir);
+    export_schema(&schema_for!(AdminResponse), &out_dir);
+    export_schema(&schema_for!(ComplianceResponse), &out_dir);
+    export_schema(&schema_for!(FrozenAccountsResponse), &out_dir);
+    export_schema(&schema_for!(FlashLoanFeeResponse), &out_dir);
+    export_schema(&schema_for!(HooksResponse), &out_dir);
+    export_schema(&schema_for!(BalanceChangedHookMsg), &out_dir);
+    export_schema(&schema_for!(PermitMsg), &out_dir);
+    export_schema(&schema_for!(PermitNonceResponse), &out_dir);
+    export_schema(&schema_for!(VestingResponse), &out_dir);
+    export_schema(&schema_for!(StakingResponse), &out_dir);
+    export_schema(&schema_for!(DelegationsResponse), &out_dir);
+    export_schema(&schema_for!(ClaimsResponse), &out_dir);
+    export_schema(&schema_for!(ListChannelsResponse), &out_dir);
+    export_schema(&schema_for!(ChannelResponse), &out_dir);
+    export_schema(&schema_for!(FailedWithdrawalsResponse), &out_dir);
+    export_schema(&schema_for!(EscrowResponse), &out_dir);
+    export_schema(&schema_for!(EscrowsResponse), &out_dir);
+    export_schema(&schema_for!(StreamResponse), &out_dir);
+    export_schema(&schema_for!(MerkleStageResponse), &out_dir);
+    export_schema(&schema_for!(AirdropClaimedResponse), &out_dir);
 }
@@ -0,0 +1,191 @@
+use cosmwasm_std::{Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128};
+use cw0::Expiration;
+use cw_storage_plus::U8Key;
+use sha2::{Digest, Sha256};
+
+use crate::compliance::assert_recipient_not_frozen;
+use crate::erc20::{AirdropClaimedResponse, MerkleStageResponse};
+use crate::error::ContractError;
+use crate::hooks::{add_balance_hooks, TokenBalances};
+use crate::ledger::mint_tokens;
+use crate::state::{MerkleStage, ADMIN, AIRDROP_CLAIMS, MERKLE_STAGES};
+
+/// Admin only. Opens an airdrop stage. Each leaf of the tree is the SHA-256 hash
+/// of the claimant address followed by the amount in decimal.
+pub fn execute_register_merkle_root(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stage: u8,
+    root: String,
+    expiration: Expiration,
+    total_amount: Uint128,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if MERKLE_STAGES.has(deps.storage, U8Key::new(stage)) {
+        return Err(ContractError::StageExists { stage });
+    }
+    let mut root_hash = [0u8; 32];
+    hex::decode_to_slice(&root, &mut root_hash).map_err(|_| ContractError::InvalidMerkleRoot {})?;
+    if total_amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    if expiration.is_expired(&env.block) {
+        return Err(ContractError::Expired {});
+    }
+
+    let merkle_stage = MerkleStage {
+        root: root.clone(),
+        expiration,
+        total_amount,
+        claimed: Uint128::zero(),
+        reclaimed: false,
+    };
+    MERKLE_STAGES.save(deps.storage, U8Key::new(stage), &merkle_stage)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_merkle_root")
+        .add_attribute("stage", stage.to_string())
+        .add_attribute("root", root)
+        .add_attribute("total_amount", total_amount))
+}
+
+/// Mints amount to the sender if the proof shows it is in the tree of the stage.
+/// Every address claims at most once per stage.
+pub fn execute_claim_airdrop(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stage: u8,
+    amount: Uint128,
+    proof: Vec<String>,
+) -> Result<Response, ContractError> {
+    let mut merkle_stage = load_stage(deps.as_ref(), stage)?;
+    if merkle_stage.expiration.is_expired(&env.block) {
+        return Err(ContractError::Expired {});
+    }
+    let claim_key = (&info.sender, U8Key::new(stage));
+    if AIRDROP_CLAIMS.has(deps.storage, claim_key.clone()) {
+        return Err(ContractError::AirdropClaimed { stage });
+    }
+    assert_recipient_not_frozen(deps.storage, &info.sender)?;
+    verify_proof(&merkle_stage.root, info.sender.as_str(), amount, &proof)?;
+
+    merkle_stage.claimed = merkle_stage
+        .claimed
+        .checked_add(amount)
+        .map_err(StdError::from)?;
+    if merkle_stage.claimed > merkle_stage.total_amount {
+        return Err(ContractError::AirdropExhausted { stage });
+    }
+    let before = TokenBalances::load(deps.storage, &[&info.sender])?;
+    mint_tokens(deps.storage, env.block.height, &info.sender, amount)?;
+    MERKLE_STAGES.save(deps.storage, U8Key::new(stage), &merkle_stage)?;
+    AIRDROP_CLAIMS.save(deps.storage, claim_key, &true)?;
+
+    let res = Response::new()
+        .add_attribute("action", "claim_airdrop")
+        .add_attribute("stage", stage.to_string())
+        .add_attribute("to", info.sender)
+        .add_attribute("amount", amount);
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+/// Admin only. Mints what was left unclaimed in an expired stage to the
+/// recipient, by default the admin
+pub fn execute_reclaim_airdrop(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stage: u8,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let mut merkle_stage = load_stage(deps.as_ref(), stage)?;
+    if !merkle_stage.expiration.is_expired(&env.block) {
+        return Err(ContractError::StageNotExpired { stage });
+    }
+    let amount = merkle_stage.total_amount - merkle_stage.claimed;
+    if merkle_stage.reclaimed || amount.is_zero() {
+        return Err(ContractError::NothingToClaim {});
+    }
+    let rcpt_addr = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender,
+    };
+    assert_recipient_not_frozen(deps.storage, &rcpt_addr)?;
+
+    let before = TokenBalances::load(deps.storage, &[&rcpt_addr])?;
+    mint_tokens(deps.storage, env.block.height, &rcpt_addr, amount)?;
+    merkle_stage.reclaimed = true;
+    MERKLE_STAGES.save(deps.storage, U8Key::new(stage), &merkle_stage)?;
+
+    let res = Response::new()
+        .add_attribute("action", "reclaim_airdrop")
+        .add_attribute("stage", stage.to_string())
+        .add_attribute("to", rcpt_addr.as_str())
+        .add_attribute("amount", amount);
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+fn load_stage(deps: Deps, stage: u8) -> Result<MerkleStage, ContractError> {
+    MERKLE_STAGES
+        .may_load(deps.storage, U8Key::new(stage))?
+        .ok_or(ContractError::UnknownStage { stage })
+}
+
+/// Hashes the leaf up the tree with the proof, ordering each pair of hashes so the
+/// proof needs no left or right flags, and compares the result with the root
+fn verify_proof(
+    root: &str,
+    address: &str,
+    amount: Uint128,
+    proof: &[String],
+) -> Result<(), ContractError> {
+    let leaf = format!("{}{}", address, amount);
+    let mut hash: [u8; 32] = Sha256::digest(leaf.as_bytes()).into();
+    for step in proof {
+        let mut sibling = [0u8; 32];
+        hex::decode_to_slice(step, &mut sibling).map_err(|_| ContractError::InvalidProof {})?;
+        let (first, second) = if hash <= sibling {
+            (hash, sibling)
+        } else {
+            (sibling, hash)
+        };
+        hash = Sha256::new().chain(first).chain(second).finalize().into();
+    }
+    if hex::encode(hash) != root.to_lowercase() {
+        return Err(ContractError::InvalidProof {});
+    }
+    Ok(())
+}
+
+pub fn query_merkle_stage(deps: Deps, stage: u8) -> StdResult<MerkleStageResponse> {
+    let merkle_stage = MERKLE_STAGES.load(deps.storage, U8Key::new(stage))?;
+    Ok(MerkleStageResponse {
+        stage,
+        root: merkle_stage.root,
+        expiration: merkle_stage.expiration,
+        total_amount: merkle_stage.total_amount,
+        claimed: merkle_stage.claimed,
+        reclaimed: merkle_stage.reclaimed,
+    })
+}
+
+pub fn query_airdrop_claimed(
+    deps: Deps,
+    stage: u8,
+    address: String,
+) -> StdResult<AirdropClaimedResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let claimed = AIRDROP_CLAIMS.has(deps.storage, (&address, U8Key::new(stage)));
+    Ok(AirdropClaimedResponse { claimed })
+}
@@ -0,0 +1,200 @@
+use cosmwasm_std::{
+    Addr, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult, Storage, Uint128,
+};
+use cw_storage_plus::Bound;
+
+use crate::erc20::{ComplianceResponse, FrozenAccountsResponse};
+use crate::error::ContractError;
+use crate::hooks::{add_balance_hooks, BalanceDiff, TokenBalances};
+use crate::ledger::{decrease_denom_balance, increase_denom_balance, move_token_balance};
+use crate::state::{ComplianceConfig, ADMIN, COMPLIANCE, FROZEN_ACCOUNTS};
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+pub fn is_frozen(storage: &dyn Storage, address: &Addr) -> StdResult<bool> {
+    Ok(FROZEN_ACCOUNTS
+        .may_load(storage, address)?
+        .unwrap_or_default())
+}
+
+pub fn assert_sender_not_frozen(storage: &dyn Storage, sender: &Addr) -> Result<(), ContractError> {
+    if is_frozen(storage, sender)? {
+        return Err(ContractError::FrozenSender {
+            address: sender.to_string(),
+        });
+    }
+    Ok(())
+}
+
+pub fn assert_recipient_not_frozen(
+    storage: &dyn Storage,
+    recipient: &Addr,
+) -> Result<(), ContractError> {
+    if is_frozen(storage, recipient)? {
+        return Err(ContractError::FrozenRecipient {
+            address: recipient.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// The admin and the compliance officer share the right to freeze and seize
+fn assert_compliance_role(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    if ADMIN.is_admin(deps, sender)? {
+        return Ok(());
+    }
+    let config = COMPLIANCE.may_load(deps.storage)?.unwrap_or_default();
+    match config.officer {
+        Some(officer) if &officer == sender => Ok(()),
+        _ => Err(ContractError::Unauthorized {}),
+    }
+}
+
+pub fn execute_update_compliance(
+    deps: DepsMut,
+    info: MessageInfo,
+    officer: Option<String>,
+    recovery_address: Option<String>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let config = ComplianceConfig {
+        officer: officer
+            .map(|addr| deps.api.addr_validate(&addr))
+            .transpose()?,
+        recovery_address: recovery_address
+            .map(|addr| deps.api.addr_validate(&addr))
+            .transpose()?,
+    };
+    COMPLIANCE.save(deps.storage, &config)?;
+
+    let none = || "None".to_string();
+    Ok(Response::new()
+        .add_attribute("action", "update_compliance")
+        .add_attribute(
+            "officer",
+            config.officer.map(String::from).unwrap_or_else(none),
+        )
+        .add_attribute(
+            "recovery_address",
+            config
+                .recovery_address
+                .map(String::from)
+                .unwrap_or_else(none),
+        ))
+}
+
+pub fn execute_freeze(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    assert_compliance_role(deps.as_ref(), &info.sender)?;
+    let address = deps.api.addr_validate(&address)?;
+    FROZEN_ACCOUNTS.save(deps.storage, &address, &true)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "freeze")
+        .add_attribute("address", address))
+}
+
+pub fn execute_unfreeze(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    assert_compliance_role(deps.as_ref(), &info.sender)?;
+    let address = deps.api.addr_validate(&address)?;
+    FROZEN_ACCOUNTS.remove(deps.storage, &address);
+
+    Ok(Response::new()
+        .add_attribute("action", "unfreeze")
+        .add_attribute("address", address))
+}
+
+pub fn execute_seize(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    denom: Option<String>,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    assert_compliance_role(deps.as_ref(), &info.sender)?;
+    if amount == Uint128::zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    let recovery = COMPLIANCE
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .recovery_address
+        .ok_or(ContractError::NoRecoveryAddress {})?;
+    let address = deps.api.addr_validate(&address)?;
+
+    let diffs = match &denom {
+        Some(denom) => {
+            let from =
+                decrease_denom_balance(deps.storage, env.block.height, &address, denom, amount)?;
+            let to =
+                increase_denom_balance(deps.storage, env.block.height, &recovery, denom, amount)?;
+            vec![
+                BalanceDiff {
+                    address: address.to_string(),
+                    denom: Some(denom.clone()),
+                    old: from + amount,
+                    new: from,
+                },
+                BalanceDiff {
+                    address: recovery.to_string(),
+                    denom: Some(denom.clone()),
+                    old: to - amount,
+                    new: to,
+                },
+            ]
+        }
+        None => {
+            let before = TokenBalances::load(deps.storage, &[&address, &recovery])?;
+            move_token_balance(deps.storage, env.block.height, &address, &recovery, amount)?;
+            before.diffs(deps.storage)?
+        }
+    };
+
+    let mut res = Response::new()
+        .add_attribute("action", "seize")
+        .add_attribute("from", address)
+        .add_attribute("to", recovery)
+        .add_attribute("amount", amount);
+    if let Some(denom) = denom {
+        res = res.add_attribute("denom", denom);
+    }
+    Ok(add_balance_hooks(deps.storage, res, diffs)?)
+}
+
+pub fn query_compliance(deps: Deps) -> StdResult<ComplianceResponse> {
+    let config = COMPLIANCE.may_load(deps.storage)?.unwrap_or_default();
+    Ok(ComplianceResponse {
+        officer: config.officer.map(String::from),
+        recovery_address: config.recovery_address.map(String::from),
+    })
+}
+
+pub fn query_frozen_accounts(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<FrozenAccountsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let accounts: Result<Vec<_>, _> = FROZEN_ACCOUNTS
+        .keys(deps.storage, start, None, Order::Ascending)
+        .map(String::from_utf8)
+        .take(limit)
+        .collect();
+
+    Ok(FrozenAccountsResponse {
+        accounts: accounts?,
+    })
+}
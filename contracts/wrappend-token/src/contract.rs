@@ -1,22 +1,61 @@
-use crate::state::{BALANCES, WHITELISTED_COINS};
+use crate::airdrop::{
+    execute_claim_airdrop, execute_reclaim_airdrop, execute_register_merkle_root,
+    query_airdrop_claimed, query_merkle_stage,
+};
+use crate::compliance::{
+    assert_recipient_not_frozen, assert_sender_not_frozen, execute_freeze, execute_seize,
+    execute_unfreeze, execute_update_compliance, query_compliance, query_frozen_accounts,
+};
+use crate::error::ContractError;
+use crate::escrow::{
+    execute_approve_escrow, execute_create_escrow, execute_refund_escrow, query_escrow,
+    query_escrows_by_recipient, query_escrows_by_sender,
+};
+use crate::flash_loan::{
+    assert_no_flash_loan, execute_finish_flash_loan, execute_flash_loan,
+    execute_update_flash_loan_fee, execute_withdraw_flash_loan_fees, query_flash_loan_fee,
+};
+use crate::hooks::{add_balance_hooks, BalanceDiff, TokenBalances};
+use crate::ibc::{execute_ibc_transfer, query_channel, query_list_channels};
+use crate::ledger::{
+    decrease_denom_balance, decrease_token_balance, decrease_total_supply, denom_balance,
+    denom_balance_at_height, increase_denom_balance, increase_token_balance, increase_total_supply,
+    mint_tokens, move_token_balance, seed_total_supply, token_balance, token_balance_at_height,
+    total_supply, total_supply_at_height,
+};
+use crate::permit::{execute_permit, query_permit_nonce};
+use crate::state::{ADMIN, HOOKS, WHITELISTED_COINS};
 
+use crate::erc20::{
+    ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, TotalSupplyResponse, VestingAccount,
+};
+use crate::staking::{
+    bond, execute_add_validator, execute_claim, execute_collect_rewards, execute_compound,
+    execute_rebalance, execute_remove_validator, execute_set_validator_weights,
+    execute_update_compound_fees, execute_update_staking, is_bond_denom, query_claims,
+    query_delegations, query_staking, unbond,
+};
+use crate::stream::{
+    execute_cancel_stream, execute_create_stream, execute_withdraw_from_stream, query_stream,
+};
+use crate::vesting::{assert_vested, execute_grant_vesting, query_vesting, save_vesting};
+use crate::withdrawals::{
+    query_failed_withdrawals, reply_withdraw, take_failed_withdrawal, withdrawal_msg,
+    WITHDRAW_REPLY_ID,
+};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::{entry_point, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
-use cosmwasm_std::{to_binary, BankMsg, Coin, CosmosMsg, StdError, Uint128, Addr};
-use cw2::set_contract_version;
-use cw20::BalanceResponse;
-use cw20_base::{
-    contract::{
-        execute_mint, execute_send, execute_transfer, instantiate as cw_instantiate,
-        query_minter, query_token_info, query_balance,
-    },
-    ContractError,
-};
-use crate::erc20::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use cosmwasm_std::{to_binary, Addr, BankMsg, Coin, Reply, StdError, Uint128};
+use cw2::{get_contract_version, set_contract_version};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ReceiveMsg, TokenInfoResponse};
+use cw20_base::allowances::{deduct_allowance, query_allowance};
+use cw20_base::contract::{instantiate as cw_instantiate, query_minter};
+use cw20_base::msg::InstantiateMsg as BaseInstantiateMsg;
+use cw20_base::state::TOKEN_INFO;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "erc-20";
-const CONTRACT_VERSION: &str = "1.0.0";
+const CONTRACT_VERSION: &str = "1.1.0";
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -25,12 +64,62 @@ pub fn instantiate(
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
-    cw_instantiate(deps.branch(), env.clone(), info.clone(), msg)?;
+    let cap = msg.get_cap();
+    // initial balances go to the snapshotted ledger rather than the cw20-base one
+    let base_msg = BaseInstantiateMsg {
+        name: msg.name,
+        symbol: msg.symbol,
+        decimals: msg.decimals,
+        initial_balances: vec![],
+        mint: msg.mint,
+        marketing: msg.marketing,
+    };
+    cw_instantiate(deps.branch(), env.clone(), info.clone(), base_msg)?;
+    let mut total_supply = create_accounts(&mut deps, &env, &msg.initial_balances)?;
+    total_supply += create_vesting_accounts(&mut deps, &env, &msg.vesting)?;
+    if let Some(limit) = cap {
+        if total_supply > limit {
+            return Err(StdError::generic_err("Initial supply greater than cap").into());
+        }
+    }
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    ADMIN.set(deps.branch(), Some(info.sender))?;
 
     Ok(Response::new().add_attribute("action", "erc20_contract_intantiated"))
 }
 
+fn create_accounts(deps: &mut DepsMut, env: &Env, accounts: &[Cw20Coin]) -> StdResult<Uint128> {
+    let mut total_supply = Uint128::zero();
+    for row in accounts {
+        let address = deps.api.addr_validate(&row.address)?;
+        increase_token_balance(deps.storage, env.block.height, &address, row.amount)?;
+        total_supply += row.amount;
+    }
+    increase_total_supply(deps.storage, env.block.height, total_supply)?;
+    Ok(total_supply)
+}
+
+fn create_vesting_accounts(
+    deps: &mut DepsMut,
+    env: &Env,
+    accounts: &[VestingAccount],
+) -> Result<Uint128, ContractError> {
+    let mut total_supply = Uint128::zero();
+    for row in accounts {
+        let address = deps.api.addr_validate(&row.address)?;
+        save_vesting(deps.storage, &env.block, &address, &row.schedule)?;
+        increase_token_balance(
+            deps.storage,
+            env.block.height,
+            &address,
+            row.schedule.amount,
+        )?;
+        total_supply += row.schedule.amount;
+    }
+    increase_total_supply(deps.storage, env.block.height, total_supply)?;
+    Ok(total_supply)
+}
+
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
@@ -50,15 +139,442 @@ pub fn execute(
             recipient,
         } => withdraw(deps, env, info, denom, amount, recipient),
         ExecuteMsg::Transfer { recipient, amount } => {
-            execute_transfer(deps, env, info, recipient, amount)
+            transfer(deps, env, info, recipient, amount)
+        }
+        ExecuteMsg::BatchTransfer { transfers } => batch_transfer(deps, env, info, transfers),
+        ExecuteMsg::BatchWithdraw { denom, withdrawals } => {
+            batch_withdraw(deps, env, info, denom, withdrawals)
         }
         ExecuteMsg::Send {
             contract,
             amount,
             msg,
-        } => execute_send(deps, env, info, contract, amount, msg),
-        ExecuteMsg::Mint { recipient, amount } => execute_mint(deps, env, info, recipient, amount)
+        } => send(deps, env, info, contract, amount, msg),
+        ExecuteMsg::TransferFrom {
+            owner,
+            recipient,
+            amount,
+        } => transfer_from(deps, env, info, owner, recipient, amount),
+        ExecuteMsg::SendFrom {
+            owner,
+            contract,
+            amount,
+            msg,
+        } => send_from(deps, env, info, owner, contract, amount, msg),
+        ExecuteMsg::Permit {
+            owner_pubkey,
+            spender,
+            amount,
+            expires,
+            nonce,
+            signature,
+        } => execute_permit(
+            deps,
+            env,
+            info,
+            owner_pubkey,
+            spender,
+            amount,
+            expires,
+            nonce,
+            signature,
+        ),
+        ExecuteMsg::Mint { recipient, amount } => mint(deps, env, info, recipient, amount),
+        ExecuteMsg::Burn { amount } => burn(deps, env, info, amount),
+        ExecuteMsg::UpdateStaking {
+            bond_denom,
+            unbonding_period,
+        } => execute_update_staking(deps, info, bond_denom, unbonding_period),
+        ExecuteMsg::AddValidator { validator } => execute_add_validator(deps, info, validator),
+        ExecuteMsg::RemoveValidator { validator } => {
+            execute_remove_validator(deps, env, info, validator)
+        }
+        ExecuteMsg::SetValidatorWeights { weights } => {
+            execute_set_validator_weights(deps, info, weights)
+        }
+        ExecuteMsg::Rebalance {} => execute_rebalance(deps, env, info),
+        ExecuteMsg::CollectRewards {} => execute_collect_rewards(deps, env, info),
+        ExecuteMsg::Compound {} => execute_compound(deps, env, info),
+        ExecuteMsg::UpdateCompoundFees {
+            keeper_tip,
+            protocol_fee,
+            fee_recipient,
+        } => execute_update_compound_fees(deps, info, keeper_tip, protocol_fee, fee_recipient),
+        ExecuteMsg::Claim {} => execute_claim(deps, env, info),
+        ExecuteMsg::CreateEscrow {
+            recipient,
+            arbiter,
+            amount,
+            expiration,
+        } => execute_create_escrow(deps, env, info, recipient, arbiter, amount, expiration),
+        ExecuteMsg::ApproveEscrow { id } => execute_approve_escrow(deps, env, info, id),
+        ExecuteMsg::RefundEscrow { id } => execute_refund_escrow(deps, env, id),
+        ExecuteMsg::CreateStream {
+            recipient,
+            amount,
+            start,
+            end,
+        } => execute_create_stream(deps, env, info, recipient, amount, start, end),
+        ExecuteMsg::WithdrawFromStream { id } => execute_withdraw_from_stream(deps, env, info, id),
+        ExecuteMsg::CancelStream { id } => execute_cancel_stream(deps, env, info, id),
+        ExecuteMsg::RegisterMerkleRoot {
+            stage,
+            root,
+            expiration,
+            total_amount,
+        } => execute_register_merkle_root(deps, env, info, stage, root, expiration, total_amount),
+        ExecuteMsg::ClaimAirdrop {
+            stage,
+            amount,
+            proof,
+        } => execute_claim_airdrop(deps, env, info, stage, amount, proof),
+        ExecuteMsg::ReclaimAirdrop { stage, recipient } => {
+            execute_reclaim_airdrop(deps, env, info, stage, recipient)
+        }
+        ExecuteMsg::RetryWithdrawal { id } => {
+            let failed = take_failed_withdrawal(deps.storage, &info.sender, id)?;
+            let recipient = Some(failed.recipient.into());
+            withdraw(deps, env, info, failed.denom, failed.amount, recipient)
+        }
+        ExecuteMsg::IbcTransfer {
+            channel,
+            remote_address,
+            amount,
+            timeout,
+        } => execute_ibc_transfer(deps, env, info, channel, remote_address, amount, timeout),
+        ExecuteMsg::GrantVesting {
+            recipient,
+            schedule,
+        } => execute_grant_vesting(deps, env, info, recipient, schedule),
+        ExecuteMsg::FlashLoan {
+            denom,
+            amount,
+            callback_contract,
+            msg,
+        } => execute_flash_loan(deps, env, info, denom, amount, callback_contract, msg),
+        ExecuteMsg::FinishFlashLoan {} => execute_finish_flash_loan(deps, env, info),
+        ExecuteMsg::UpdateFlashLoanFee { fee } => execute_update_flash_loan_fee(deps, info, fee),
+        ExecuteMsg::WithdrawFlashLoanFees { denom, recipient } => {
+            execute_withdraw_flash_loan_fees(deps, info, denom, recipient)
+        }
+        ExecuteMsg::UpdateAdmin { admin } => {
+            let admin = admin
+                .map(|addr| deps.api.addr_validate(&addr))
+                .transpose()?;
+            Ok(ADMIN.execute_update_admin(deps, info, admin)?)
+        }
+        ExecuteMsg::UpdateCompliance {
+            officer,
+            recovery_address,
+        } => execute_update_compliance(deps, info, officer, recovery_address),
+        ExecuteMsg::Freeze { address } => execute_freeze(deps, info, address),
+        ExecuteMsg::Unfreeze { address } => execute_unfreeze(deps, info, address),
+        ExecuteMsg::Seize {
+            address,
+            denom,
+            amount,
+        } => execute_seize(deps, env, info, address, denom, amount),
+        ExecuteMsg::AddHook { addr } => {
+            let addr = deps.api.addr_validate(&addr)?;
+            Ok(HOOKS.execute_add_hook(&ADMIN, deps, info, addr)?)
+        }
+        ExecuteMsg::RemoveHook { addr } => {
+            let addr = deps.api.addr_validate(&addr)?;
+            Ok(HOOKS.execute_remove_hook(&ADMIN, deps, info, addr)?)
+        }
+    }
+}
+
+/// Rejects the move if either side of it is frozen
+fn assert_can_move(deps: Deps, sender: &Addr, recipient: &str) -> Result<Addr, ContractError> {
+    assert_sender_not_frozen(deps.storage, sender)?;
+    let rcpt_addr = deps.api.addr_validate(recipient)?;
+    assert_recipient_not_frozen(deps.storage, &rcpt_addr)?;
+    Ok(rcpt_addr)
+}
+
+fn transfer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    if amount == Uint128::zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    let rcpt_addr = assert_can_move(deps.as_ref(), &info.sender, &recipient)?;
+
+    assert_vested(deps.storage, &env.block, &info.sender, amount)?;
+    let before = TokenBalances::load(deps.storage, &[&info.sender, &rcpt_addr])?;
+    move_token_balance(
+        deps.storage,
+        env.block.height,
+        &info.sender,
+        &rcpt_addr,
+        amount,
+    )?;
+
+    let res = Response::new()
+        .add_attribute("action", "transfer")
+        .add_attribute("from", info.sender)
+        .add_attribute("to", recipient)
+        .add_attribute("amount", amount);
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+/// Moves tokens from the sender to every recipient, all or nothing
+fn batch_transfer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    transfers: Vec<(String, Uint128)>,
+) -> Result<Response, ContractError> {
+    let (recipients, total) = validate_batch(deps.as_ref(), &info.sender, &transfers)?;
+    // checked up front so a failing batch leaves every balance untouched
+    assert_vested(deps.storage, &env.block, &info.sender, total)?;
+    token_balance(deps.storage, &info.sender)?
+        .checked_sub(total)
+        .map_err(StdError::from)?;
+
+    let mut accounts: Vec<&Addr> = vec![&info.sender];
+    accounts.extend(recipients.iter().map(|(addr, _)| addr));
+    let before = TokenBalances::load(deps.storage, &accounts)?;
+    for (rcpt_addr, amount) in &recipients {
+        move_token_balance(
+            deps.storage,
+            env.block.height,
+            &info.sender,
+            rcpt_addr,
+            *amount,
+        )?;
+    }
+
+    let res = Response::new()
+        .add_attribute("action", "batch_transfer")
+        .add_attribute("from", info.sender)
+        .add_attribute("recipients", recipients.len().to_string())
+        .add_attribute("amount", total);
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+/// Validates every recipient and amount of a batch, returning them with the total
+fn validate_batch(
+    deps: Deps,
+    sender: &Addr,
+    batch: &[(String, Uint128)],
+) -> Result<(Vec<(Addr, Uint128)>, Uint128), ContractError> {
+    if batch.is_empty() {
+        return Err(ContractError::EmptyBatch {});
+    }
+    let mut total = Uint128::zero();
+    let mut recipients = Vec::with_capacity(batch.len());
+    for (recipient, amount) in batch {
+        if amount.is_zero() {
+            return Err(ContractError::InvalidZeroAmount {});
+        }
+        let rcpt_addr = assert_can_move(deps, sender, recipient)?;
+        total = total.checked_add(*amount).map_err(StdError::from)?;
+        recipients.push((rcpt_addr, *amount));
+    }
+    Ok((recipients, total))
+}
+
+fn send(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: String,
+    amount: Uint128,
+    msg: Binary,
+) -> Result<Response, ContractError> {
+    if amount == Uint128::zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    let rcpt_addr = assert_can_move(deps.as_ref(), &info.sender, &contract)?;
+
+    // move the tokens to the contract
+    assert_vested(deps.storage, &env.block, &info.sender, amount)?;
+    let before = TokenBalances::load(deps.storage, &[&info.sender, &rcpt_addr])?;
+    move_token_balance(
+        deps.storage,
+        env.block.height,
+        &info.sender,
+        &rcpt_addr,
+        amount,
+    )?;
+
+    let res = Response::new()
+        .add_attribute("action", "send")
+        .add_attribute("from", &info.sender)
+        .add_attribute("to", &contract)
+        .add_attribute("amount", amount)
+        .add_message(
+            Cw20ReceiveMsg {
+                sender: info.sender.into(),
+                amount,
+                msg,
+            }
+            .into_cosmos_msg(contract)?,
+        );
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+fn transfer_from(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    owner: String,
+    recipient: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let owner_addr = deps.api.addr_validate(&owner)?;
+    let rcpt_addr = assert_can_move(deps.as_ref(), &owner_addr, &recipient)?;
+
+    // deduct allowance before doing anything else have enough allowance
+    deduct_allowance(deps.storage, &owner_addr, &info.sender, &env.block, amount)?;
+
+    assert_vested(deps.storage, &env.block, &owner_addr, amount)?;
+    let before = TokenBalances::load(deps.storage, &[&owner_addr, &rcpt_addr])?;
+    move_token_balance(
+        deps.storage,
+        env.block.height,
+        &owner_addr,
+        &rcpt_addr,
+        amount,
+    )?;
+
+    let res = Response::new()
+        .add_attribute("action", "transfer_from")
+        .add_attribute("from", owner)
+        .add_attribute("to", recipient)
+        .add_attribute("by", info.sender)
+        .add_attribute("amount", amount);
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+fn send_from(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    owner: String,
+    contract: String,
+    amount: Uint128,
+    msg: Binary,
+) -> Result<Response, ContractError> {
+    let owner_addr = deps.api.addr_validate(&owner)?;
+    let rcpt_addr = assert_can_move(deps.as_ref(), &owner_addr, &contract)?;
+
+    // deduct allowance before doing anything else have enough allowance
+    deduct_allowance(deps.storage, &owner_addr, &info.sender, &env.block, amount)?;
+
+    // move the tokens to the contract
+    assert_vested(deps.storage, &env.block, &owner_addr, amount)?;
+    let before = TokenBalances::load(deps.storage, &[&owner_addr, &rcpt_addr])?;
+    move_token_balance(
+        deps.storage,
+        env.block.height,
+        &owner_addr,
+        &rcpt_addr,
+        amount,
+    )?;
+
+    let res = Response::new()
+        .add_attribute("action", "send_from")
+        .add_attribute("from", &owner)
+        .add_attribute("to", &contract)
+        .add_attribute("by", &info.sender)
+        .add_attribute("amount", amount)
+        .add_message(
+            Cw20ReceiveMsg {
+                sender: info.sender.into(),
+                amount,
+                msg,
+            }
+            .into_cosmos_msg(contract)?,
+        );
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+fn mint(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    if amount == Uint128::zero() {
+        return Err(ContractError::InvalidZeroAmount {});
     }
+
+    let config = TOKEN_INFO.load(deps.storage)?;
+    if config.mint.is_none() || config.mint.as_ref().unwrap().minter != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let rcpt_addr = deps.api.addr_validate(&recipient)?;
+    assert_recipient_not_frozen(deps.storage, &rcpt_addr)?;
+
+    let before = TokenBalances::load(deps.storage, &[&rcpt_addr])?;
+    mint_tokens(deps.storage, env.block.height, &rcpt_addr, amount)?;
+
+    let res = Response::new()
+        .add_attribute("action", "mint")
+        .add_attribute("to", recipient)
+        .add_attribute("amount", amount);
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+fn burn(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    if amount == Uint128::zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    assert_sender_not_frozen(deps.storage, &info.sender)?;
+
+    // lower balance and reduce total_supply
+    assert_vested(deps.storage, &env.block, &info.sender, amount)?;
+    let before = TokenBalances::load(deps.storage, &[&info.sender])?;
+    decrease_token_balance(deps.storage, env.block.height, &info.sender, amount)?;
+    decrease_total_supply(deps.storage, env.block.height, amount)?;
+
+    let res = Response::new()
+        .add_attribute("action", "burn")
+        .add_attribute("from", &info.sender)
+        .add_attribute("amount", amount);
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
 }
 
 fn set_coin_white_listing(
@@ -71,7 +587,7 @@ fn set_coin_white_listing(
     Ok(Response::default())
 }
 
-fn deposit(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+fn deposit(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
     let recipient: String = info.sender.to_string();
     let coins: Vec<Coin> = info.funds.clone();
 
@@ -83,30 +599,36 @@ fn deposit(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Response, Cont
     let denom: &str = &coins[0].denom;
     let amount: Uint128 = coins[0].amount;
     is_white_listed_denom(deps.as_ref(), &coins[0].denom)?;
+    assert_recipient_not_frozen(deps.storage, &info.sender)?;
+    assert_no_flash_loan(deps.storage)?;
 
-    
     if amount == Uint128::zero() {
         return Err(ContractError::InvalidZeroAmount {});
     }
+    if is_bond_denom(deps.storage, denom)? {
+        return bond(deps, env, info, amount);
+    }
 
     // add amount to recipient balance
-    BALANCES.update(
-        deps.storage,
-        (&info.sender, denom),
-        |balance: Option<Uint128>| -> StdResult<_> { Ok(balance.unwrap_or_default() + amount) },
-    )?;
+    let new = increase_denom_balance(deps.storage, env.block.height, &info.sender, denom, amount)?;
+    let diff = BalanceDiff {
+        address: recipient.clone(),
+        denom: Some(denom.to_string()),
+        old: new - amount,
+        new,
+    };
 
     let res = Response::new()
         .add_attribute("action", "deposit")
         .add_attribute("to", recipient)
         .add_attribute("denom", denom.to_string())
         .add_attribute("amount", amount);
-    Ok(res)
+    Ok(add_balance_hooks(deps.storage, res, vec![diff])?)
 }
 
 fn withdraw(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     denom: String,
     amount: Uint128,
@@ -119,63 +641,230 @@ fn withdraw(
         },
         None => info.sender.clone(),
     };
-    
-    let exec_msg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: receiver.to_string(),
-        amount: vec![Coin {
-            denom: denom.clone(),
-            amount: amount,
-        }],
-    });
+    assert_sender_not_frozen(deps.storage, &info.sender)?;
+    assert_recipient_not_frozen(deps.storage, &receiver)?;
+    assert_no_flash_loan(deps.storage)?;
+
     if amount == Uint128::zero() {
         return Err(ContractError::InvalidZeroAmount {});
     }
+    // in staking mode the bond denom is paid out after unbonding, once the coins
+    // deposited before staking was on are withdrawn
+    let legacy = denom_balance(deps.storage, &info.sender, &denom)?;
+    if legacy.is_zero() && is_bond_denom(deps.storage, &denom)? {
+        return unbond(deps, env, info, amount, receiver);
+    }
 
-    // lower balance
-    BALANCES.update(
-        deps.storage,
-        (&receiver, &denom),
-        |balance: Option<Uint128>| -> StdResult<_> {
-            Ok(balance.unwrap_or_default().checked_sub(amount)?)
-        },
-    )?;
+    // lower balance, the hooks hear of it once the send went through
+    decrease_denom_balance(deps.storage, env.block.height, &info.sender, &denom, amount)?;
+    let send = withdrawal_msg(deps.storage, &info.sender, &receiver, &denom, amount)?;
 
-    let res = Response::new()
+    Ok(Response::new()
         .add_attribute("action", "withdraw")
-        .add_message(exec_msg)
-        .add_attribute("from", receiver.to_string())
+        .add_submessage(send)
+        .add_attribute("from", info.sender.to_string())
+        .add_attribute("to", receiver.to_string())
         .add_attribute("denom", denom)
-        .add_attribute("amount", amount);
-    Ok(res)
+        .add_attribute("amount", amount))
+}
+
+/// Pays deposited coins of one denom to many recipients, all or nothing. The
+/// amounts of a recipient listed twice go out in a single bank send.
+fn batch_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: String,
+    withdrawals: Vec<(String, Uint128)>,
+) -> Result<Response, ContractError> {
+    is_white_listed_denom(deps.as_ref(), &denom)?;
+    // unbonding pays out later to a single receiver
+    let legacy = denom_balance(deps.storage, &info.sender, &denom)?;
+    if legacy.is_zero() && is_bond_denom(deps.storage, &denom)? {
+        return Err(ContractError::BatchUnbond {});
+    }
+    assert_no_flash_loan(deps.storage)?;
+    let (recipients, total) = validate_batch(deps.as_ref(), &info.sender, &withdrawals)?;
+
+    let new = decrease_denom_balance(deps.storage, env.block.height, &info.sender, &denom, total)?;
+    let diff = BalanceDiff {
+        address: info.sender.to_string(),
+        denom: Some(denom.clone()),
+        old: new + total,
+        new,
+    };
+
+    let mut payouts: Vec<(Addr, Uint128)> = vec![];
+    for (rcpt_addr, amount) in recipients {
+        match payouts.iter_mut().find(|(addr, _)| *addr == rcpt_addr) {
+            Some((_, paid)) => *paid += amount,
+            None => payouts.push((rcpt_addr, amount)),
+        }
+    }
+    let sends = payouts.iter().map(|(rcpt_addr, amount)| BankMsg::Send {
+        to_address: rcpt_addr.to_string(),
+        amount: vec![Coin::new(amount.u128(), denom.clone())],
+    });
+
+    let res = Response::new()
+        .add_attribute("action", "batch_withdraw")
+        .add_messages(sends)
+        .add_attribute("from", info.sender)
+        .add_attribute("recipients", payouts.len().to_string())
+        .add_attribute("denom", denom)
+        .add_attribute("amount", total);
+    Ok(add_balance_hooks(deps.storage, res, vec![diff])?)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        WITHDRAW_REPLY_ID => reply_withdraw(deps, env, msg),
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(mut deps: DepsMut, env: Env, msg: MigrateMsg) -> StdResult<Response> {
+    let ver = get_contract_version(deps.storage)?;
+    // ensure we are migrating from an allowed contract
+    if ver.contract != CONTRACT_NAME {
+        return Err(StdError::generic_err("Can only upgrade from same type"));
+    }
+    if ver.version.as_str() >= CONTRACT_VERSION {
+        return Err(StdError::generic_err("Cannot upgrade from a newer version"));
+    }
+    // contracts from before the snapshotted supply only have it in TOKEN_INFO
+    seed_total_supply(deps.storage, env.block.height)?;
+    // nor an admin
+    let current = match ADMIN.get(deps.as_ref()) {
+        Err(StdError::NotFound { .. }) => None,
+        admin => admin?,
+    };
+    if let (None, Some(admin)) = (current, msg.admin) {
+        let admin = deps.api.addr_validate(&admin)?;
+        ADMIN.set(deps.branch(), Some(admin))?;
+    }
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new().add_attribute("action", "migrate"))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Balance { address } => to_binary(&query_balance(deps, address)?),
         QueryMsg::BalanceDenom { address, denom } => to_binary(&query_balance_info(deps, address, denom)?),
+        QueryMsg::BalanceAtHeight {
+            address,
+            height,
+            denom,
+        } => to_binary(&query_balance_at_height(deps, address, height, denom)?),
         QueryMsg::TokenInfo {} => to_binary(&query_token_info(deps)?),
+        QueryMsg::TotalSupplyAtHeight { height } => {
+            to_binary(&query_total_supply_at_height(deps, height)?)
+        }
         QueryMsg::Minter {} => to_binary(&query_minter(deps)?),
+        QueryMsg::Allowance { owner, spender } => {
+            to_binary(&query_allowance(deps, owner, spender)?)
+        }
+        QueryMsg::Escrow { id } => to_binary(&query_escrow(deps, id)?),
+        QueryMsg::EscrowsBySender {
+            sender,
+            start_after,
+            limit,
+        } => to_binary(&query_escrows_by_sender(deps, sender, start_after, limit)?),
+        QueryMsg::EscrowsByRecipient {
+            recipient,
+            start_after,
+            limit,
+        } => to_binary(&query_escrows_by_recipient(
+            deps,
+            recipient,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::Stream { id } => to_binary(&query_stream(deps, env, id)?),
+        QueryMsg::MerkleStage { stage } => to_binary(&query_merkle_stage(deps, stage)?),
+        QueryMsg::AirdropClaimed { stage, address } => {
+            to_binary(&query_airdrop_claimed(deps, stage, address)?)
+        }
+        QueryMsg::FailedWithdrawals {
+            address,
+            start_after,
+            limit,
+        } => to_binary(&query_failed_withdrawals(
+            deps,
+            address,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::ListChannels {} => to_binary(&query_list_channels(deps)?),
+        QueryMsg::Channel { id } => to_binary(&query_channel(deps, id)?),
+        QueryMsg::Staking {} => to_binary(&query_staking(deps)?),
+        QueryMsg::Delegations {} => to_binary(&query_delegations(deps)?),
+        QueryMsg::Claims { address } => to_binary(&query_claims(deps, address)?),
+        QueryMsg::Vesting { address } => to_binary(&query_vesting(deps, env, address)?),
+        QueryMsg::PermitNonce { owner } => to_binary(&query_permit_nonce(deps, owner)?),
+        QueryMsg::FlashLoanFee {} => to_binary(&query_flash_loan_fee(deps)?),
+        QueryMsg::Hooks {} => to_binary(&HOOKS.query_hooks(deps)?),
+        QueryMsg::Admin {} => to_binary(&ADMIN.query_admin(deps)?),
+        QueryMsg::Compliance {} => to_binary(&query_compliance(deps)?),
+        QueryMsg::FrozenAccounts { start_after, limit } => {
+            to_binary(&query_frozen_accounts(deps, start_after, limit)?)
+        }
     }
 }
 
+pub fn query_balance(deps: Deps, address: String) -> StdResult<BalanceResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let balance = token_balance(deps.storage, &address)?;
+    Ok(BalanceResponse { balance })
+}
+
 fn query_balance_info(deps: Deps, address: String, denom: String)-> StdResult<BalanceResponse> {
     let address = deps.api.addr_validate(&address)?;
-    let balance = BALANCES
-        .may_load(deps.storage, (&address, &denom))?
-        .unwrap_or_default();
+    let balance = denom_balance(deps.storage, &address, &denom)?;
     Ok(BalanceResponse { balance })
 }
 
-fn is_white_listed_denom(deps: Deps, denom: &str) -> Result<bool, ContractError> {
-    let is_white_listed_coin: bool = match WHITELISTED_COINS.load(deps.storage, denom) {
-        Ok(bool_flag) => bool_flag,
-        Err(_) => false,
+pub fn query_balance_at_height(
+    deps: Deps,
+    address: String,
+    height: u64,
+    denom: Option<String>,
+) -> StdResult<BalanceResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let balance = match denom {
+        Some(denom) => denom_balance_at_height(deps.storage, &address, &denom, height)?,
+        None => token_balance_at_height(deps.storage, &address, height)?,
     };
+    Ok(BalanceResponse { balance })
+}
+
+pub fn query_token_info(deps: Deps) -> StdResult<TokenInfoResponse> {
+    let info = TOKEN_INFO.load(deps.storage)?;
+    Ok(TokenInfoResponse {
+        name: info.name,
+        symbol: info.symbol,
+        decimals: info.decimals,
+        total_supply: total_supply(deps.storage)?,
+    })
+}
+
+pub fn query_total_supply_at_height(deps: Deps, height: u64) -> StdResult<TotalSupplyResponse> {
+    let total_supply = total_supply_at_height(deps.storage, height)?;
+    Ok(TotalSupplyResponse { total_supply })
+}
+
+pub(crate) fn is_white_listed_denom(deps: Deps, denom: &str) -> Result<bool, ContractError> {
+    let is_white_listed_coin: bool = WHITELISTED_COINS
+        .load(deps.storage, denom)
+        .unwrap_or_default();
     if is_white_listed_coin {
         return Ok(is_white_listed_coin);
     }
-    return Err(ContractError::Std(StdError::generic_err(
+    Err(ContractError::Std(StdError::generic_err(
         "the coin is not whitelisted",
-    )));
+    )))
 }
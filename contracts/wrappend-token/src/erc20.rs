@@ -1,9 +1,39 @@
-pub use cw20_base::msg::InstantiateMsg;
-
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Binary, Uint128};
+use cosmwasm_std::{Binary, Coin, Decimal, Uint128};
+use cw0::Duration;
+use cw20::{Cw20Coin, Expiration, MinterResponse};
+use cw20_base::msg::InstantiateMarketingInfo;
+
+pub use crate::state::{ChannelInfo, Escrow, FailedWithdrawal, Stream};
+use crate::vesting::VestingSchedule;
+
+/// The cw20-base instantiate message, plus allocations minted under a vesting schedule
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct InstantiateMsg {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub initial_balances: Vec<Cw20Coin>,
+    pub mint: Option<MinterResponse>,
+    pub marketing: Option<InstantiateMarketingInfo>,
+    /// Minted on top of initial_balances and locked until vested
+    #[serde(default)]
+    pub vesting: Vec<VestingAccount>,
+}
+
+impl InstantiateMsg {
+    pub fn get_cap(&self) -> Option<Uint128> {
+        self.mint.as_ref().and_then(|v| v.cap)
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct VestingAccount {
+    pub address: String,
+    pub schedule: VestingSchedule,
+}
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
@@ -14,7 +44,10 @@ pub enum ExecuteMsg {
     /// coins in user address
     Deposit {},
     /// Withdraw is a base messgae to withdraw native coins from contract address and burn equivalent
-    /// coins from user address
+    /// coins from user address. The amount is in coins of denom. In staking mode the bond
+    /// denom is unbonded, burning the wrapped tokens worth amount at the exchange rate,
+    /// and paid out by Claim once the unbonding period is over. Bond denom coins deposited
+    /// before staking mode was on are withdrawn first, right away.
     Withdraw {
         denom: String,
         amount: Uint128,
@@ -22,6 +55,17 @@ pub enum ExecuteMsg {
     },
     /// Transfer is a base message to move tokens to another account without triggering actions
     Transfer { recipient: String, amount: Uint128 },
+    /// Moves tokens to every recipient in one go. Fails as a whole if any address is
+    /// invalid or frozen, or the balance does not cover the total.
+    BatchTransfer { transfers: Vec<(String, Uint128)> },
+    /// Withdraws deposited native coins of denom to many recipients in one go, with
+    /// one bank send per recipient. Fails as a whole like BatchTransfer. The amounts
+    /// are in coins of denom. In staking mode only bond denom coins deposited before
+    /// staking mode was on can be withdrawn this way.
+    BatchWithdraw {
+        denom: String,
+        withdrawals: Vec<(String, Uint128)>,
+    },
     /// Send is a base message to transfer tokens to a contract and trigger an action
     /// on the receiving contract.
     Send {
@@ -29,9 +73,180 @@ pub enum ExecuteMsg {
         amount: Uint128,
         msg: Binary,
     },
+    /// Transfers amount tokens from owner -> recipient
+    /// if `env.sender` has sufficient pre-approval.
+    TransferFrom {
+        owner: String,
+        recipient: String,
+        amount: Uint128,
+    },
+    /// Sends amount tokens from owner -> contract
+    /// if `env.sender` has sufficient pre-approval.
+    SendFrom {
+        owner: String,
+        contract: String,
+        amount: Uint128,
+        msg: Binary,
+    },
+    /// Sets the spender's allowance over the owner's tokens from a signed permit, so a
+    /// relayer can pay the gas. The signature is over the SHA-256 of the JSON encoded
+    /// `permit::PermitMsg` and each owner nonce can be used only once, in order.
+    Permit {
+        owner_pubkey: Binary,
+        spender: String,
+        amount: Uint128,
+        expires: Expiration,
+        nonce: u64,
+        signature: Binary,
+    },
     /// Only with the "mintable" extension. If authorized, creates amount new tokens
     /// and adds to the recipient balance.
     Mint { recipient: String, amount: Uint128 },
+    /// Burn is a base message to destroy tokens forever
+    Burn { amount: Uint128 },
+    /// Admin only. Moves schedule.amount from the admin balance to the recipient,
+    /// locked until vested. An account holds one schedule at a time.
+    GrantVesting {
+        recipient: String,
+        schedule: VestingSchedule,
+    },
+    /// Admin only. Registers a contract to receive a BalanceChangedHook message
+    /// whenever balances change
+    AddHook { addr: String },
+    /// Admin only. Unregisters a hook added with AddHook
+    RemoveHook { addr: String },
+    /// Lends native coins of a whitelisted denom from the reserves to callback_contract,
+    /// executing msg on it with the coins attached. Booked flash loan fees are not lent.
+    /// By the end of the transaction the contract balance of the denom must be back to
+    /// what it was plus the flash loan fee, or it all reverts.
+    FlashLoan {
+        denom: String,
+        amount: Uint128,
+        callback_contract: String,
+        msg: Binary,
+    },
+    /// Internal. Dispatched by FlashLoan after the callback to check the repayment
+    FinishFlashLoan {},
+    /// Admin only. Sets the fee charged on flash loans as a fraction of the amount
+    UpdateFlashLoanFee { fee: Decimal },
+    /// Admin only. Sends the flash loan fees collected in denom to the recipient, by
+    /// default the admin
+    WithdrawFlashLoanFees {
+        denom: String,
+        recipient: Option<String>,
+    },
+    /// Admin only. Hands the admin role to another account, or drops it when unset
+    UpdateAdmin { admin: Option<String> },
+    /// Admin only. Sets the compliance officer and the address seized funds are sent to
+    UpdateCompliance {
+        officer: Option<String>,
+        recovery_address: Option<String>,
+    },
+    /// Admin or compliance officer. Blocks the account from Transfer, Send, Withdraw, Claim
+    /// and from receiving tokens
+    Freeze { address: String },
+    /// Admin or compliance officer. Lifts a freeze placed with Freeze
+    Unfreeze { address: String },
+    /// Admin only. Turns on liquid staking of bond_denom, or updates its settings.
+    /// The bond denom cannot change while coins are staked.
+    UpdateStaking {
+        bond_denom: String,
+        unbonding_period: Duration,
+    },
+    /// Admin only. Adds a validator deposits are delegated to, with weight 1
+    AddValidator { validator: String },
+    /// Admin only. Sets the target share of the stake of validators in the set,
+    /// relative to the sum of all weights. New deposits follow the weights, use
+    /// Rebalance to move the existing stake.
+    SetValidatorWeights { weights: Vec<ValidatorWeight> },
+    /// Admin only. Redelegates stake toward the target weights, as far as the
+    /// redelegation limits of the staking module allow
+    Rebalance {},
+    /// Admin only. Removes a validator, redelegating its stake to the rest of the set
+    RemoveValidator { validator: String },
+    /// Withdraws the staking rewards of the pool into the contract, raising the
+    /// exchange rate of the wrapped token. Anyone may call it.
+    CollectRewards {},
+    /// Withdraws the staking rewards of the pool, pays the keeper tip to the sender and
+    /// the protocol fee to the fee recipient, and delegates the rest pro rata to the
+    /// current delegations. Anyone may call it.
+    Compound {},
+    /// Admin only. Sets the shares of compounded rewards paid as keeper tip and
+    /// protocol fee. A protocol fee needs a fee recipient.
+    UpdateCompoundFees {
+        keeper_tip: Decimal,
+        protocol_fee: Decimal,
+        fee_recipient: Option<String>,
+    },
+    /// Pays out the unbonded coins of the sender whose unbonding period is over
+    Claim {},
+    /// Sends a withdrawal whose bank send failed again. Its amount was put back in
+    /// the sender's balance when it failed.
+    RetryWithdrawal { id: u64 },
+    /// Locks amount tokens of the sender for the recipient until the arbiter
+    /// approves the escrow, or returns them to the sender once it expires
+    CreateEscrow {
+        recipient: String,
+        arbiter: String,
+        amount: Uint128,
+        expiration: Expiration,
+    },
+    /// Arbiter only. Pays the escrow to its recipient, unless it has expired
+    ApproveEscrow { id: u64 },
+    /// Returns an expired escrow to its sender. Anyone may call it.
+    RefundEscrow { id: u64 },
+    /// Locks amount tokens of the sender and streams them to the recipient linearly
+    /// between start and end, in seconds since the epoch
+    CreateStream {
+        recipient: String,
+        amount: Uint128,
+        start: u64,
+        end: u64,
+    },
+    /// Recipient only. Pays out the part of the stream earned and not withdrawn yet
+    WithdrawFromStream { id: u64 },
+    /// Sender only. Closes the stream, paying the recipient what was earned so far
+    /// and returning the rest to the sender
+    CancelStream { id: u64 },
+    /// Admin only. Opens an airdrop stage with the hex encoded SHA-256 root of a tree
+    /// whose leaves hash the claimant address followed by the amount. At most
+    /// total_amount tokens are minted through claims before expiration.
+    RegisterMerkleRoot {
+        stage: u8,
+        root: String,
+        expiration: Expiration,
+        total_amount: Uint128,
+    },
+    /// Mints the airdrop of the sender for the stage, given the hex encoded hashes
+    /// on the path from its leaf to the root. Once per address and stage.
+    ClaimAirdrop {
+        stage: u8,
+        amount: Uint128,
+        proof: Vec<String>,
+    },
+    /// Admin only. Mints what was left unclaimed in an expired stage to the
+    /// recipient, by default the admin
+    ReclaimAirdrop {
+        stage: u8,
+        recipient: Option<String>,
+    },
+    /// Escrows amount tokens and sends them to remote_address over an ICS-20 channel.
+    /// They are refunded if the transfer times out, by default after 600 seconds, or
+    /// fails on the remote chain.
+    IbcTransfer {
+        channel: String,
+        remote_address: String,
+        amount: Uint128,
+        timeout: Option<u64>,
+    },
+    /// Admin or compliance officer. Moves funds from the account to the recovery address.
+    /// Seizes the wrapped token balance when denom is unset, otherwise the deposited
+    /// native balance of that denom
+    Seize {
+        address: String,
+        denom: Option<String>,
+        amount: Uint128,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -41,12 +256,260 @@ pub enum QueryMsg {
     /// Return type: BalanceResponse.
     Balance { address: String},
     BalanceDenom { address: String, denom: String },
+    /// Returns the balance of the given address at the beginning of the given block,
+    /// 0 if unset. Without a denom it is the wrapped token balance, otherwise the
+    /// deposited native balance of that denom.
+    /// Return type: BalanceResponse.
+    BalanceAtHeight {
+        address: String,
+        height: u64,
+        denom: Option<String>,
+    },
     /// Returns metadata on the contract - name, decimals, supply, etc.
     /// Return type: TokenInfoResponse.
     TokenInfo {},
+    /// Returns the total supply at the beginning of the given block.
+    /// Return type: TotalSupplyResponse.
+    TotalSupplyAtHeight {
+        height: u64,
+    },
     /// Only with "mintable" extension.
     /// Returns who can mint and the hard cap on maximum tokens after minting.
     /// Return type: MinterResponse.
     Minter {},
+    /// Returns how much spender can use from owner account, 0 if unset.
+    /// Return type: AllowanceResponse.
+    Allowance {
+        owner: String,
+        spender: String,
+    },
+    /// Returns the vesting schedule of the given address with its locked and vested
+    /// amounts, and how much of the balance can be moved now.
+    /// Return type: VestingResponse.
+    Vesting {
+        address: String,
+    },
+    /// Only in staking mode. Returns the staking settings, the pool and the
+    /// exchange rate of the wrapped token in the bond denom.
+    /// Return type: StakingResponse.
+    Staking {},
+    /// Only in staking mode. Returns the current and target stake of each validator.
+    /// Return type: DelegationsResponse.
+    Delegations {},
+    /// Returns the unbonded coins waiting to be claimed by the given address.
+    /// Return type: ClaimsResponse.
+    Claims {
+        address: String,
+    },
+    /// Returns the withdrawals of the given address whose bank send failed and
+    /// that can be retried, ordered by id.
+    /// Return type: FailedWithdrawalsResponse.
+    FailedWithdrawals {
+        address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Returns the open escrow with the given id, if any.
+    /// Return type: EscrowResponse.
+    Escrow {
+        id: u64,
+    },
+    /// Returns the open escrows created by the given address, ordered by id.
+    /// Return type: EscrowsResponse.
+    EscrowsBySender {
+        sender: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Returns the open escrows paying the given address, ordered by id.
+    /// Return type: EscrowsResponse.
+    EscrowsByRecipient {
+        recipient: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Returns the open stream with the given id, if any, with the amount streamed
+    /// so far and how much of it the recipient can withdraw now.
+    /// Return type: StreamResponse.
+    Stream {
+        id: u64,
+    },
+    /// Returns the airdrop stage with what was claimed of it.
+    /// Return type: MerkleStageResponse.
+    MerkleStage {
+        stage: u8,
+    },
+    /// Returns whether the address claimed its airdrop of the stage.
+    /// Return type: AirdropClaimedResponse.
+    AirdropClaimed {
+        stage: u8,
+        address: String,
+    },
+    /// Returns all connected ICS-20 channels.
+    /// Return type: ListChannelsResponse.
+    ListChannels {},
+    /// Returns the ICS-20 channel and the tokens sent over it and not returned yet.
+    /// Return type: ChannelResponse.
+    Channel {
+        id: String,
+    },
+    /// Returns the nonce the next permit of the owner must carry.
+    /// Return type: PermitNonceResponse.
+    PermitNonce {
+        owner: String,
+    },
+    /// Returns the fee charged on flash loans and the fees collected so far.
+    /// Return type: FlashLoanFeeResponse.
+    FlashLoanFee {},
+    /// Returns the contracts notified of balance changes.
+    /// Return type: HooksResponse.
+    Hooks {},
+    /// Returns the current admin, if any.
+    /// Return type: AdminResponse.
+    Admin {},
+    /// Returns the compliance officer and recovery address.
+    /// Return type: ComplianceResponse.
+    Compliance {},
+    /// Returns all frozen accounts, ordered by address.
+    /// Return type: FrozenAccountsResponse.
+    FrozenAccounts {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {
+    /// Becomes the admin of a contract that has none yet, as contracts instantiated
+    /// before the admin existed need one for the admin-only messages. Ignored
+    /// once an admin is set.
+    #[serde(default)]
+    pub admin: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ComplianceResponse {
+    pub officer: Option<String>,
+    pub recovery_address: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FrozenAccountsResponse {
+    pub accounts: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VestingResponse {
+    pub schedule: Option<VestingSchedule>,
+    pub locked: Uint128,
+    pub vested: Uint128,
+    pub claimable: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ValidatorWeight {
+    pub validator: String,
+    pub weight: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakingResponse {
+    pub bond_denom: String,
+    pub unbonding_period: Duration,
+    pub validators: Vec<ValidatorWeight>,
+    pub bonded: Uint128,
+    pub rewards: Uint128,
+    /// Wrapped tokens minted by bonds and not burned by unbonds yet
+    pub supply: Uint128,
+    pub keeper_tip: Decimal,
+    pub protocol_fee: Decimal,
+    pub fee_recipient: Option<String>,
+    /// Bond denom each of those wrapped tokens is worth
+    pub exchange_rate: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DelegationInfo {
+    pub validator: String,
+    pub weight: u64,
+    pub current: Uint128,
+    pub target: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DelegationsResponse {
+    pub delegations: Vec<DelegationInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ListChannelsResponse {
+    pub channels: Vec<ChannelInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ChannelResponse {
+    pub info: ChannelInfo,
+    /// Tokens sent over the channel and not returned yet
+    pub balance: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TotalSupplyResponse {
+    pub total_supply: Uint128,
+}
+
+/// Same shape as the response of cw_controllers::Hooks::query_hooks, which that
+/// crate does not export
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HooksResponse {
+    pub hooks: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FlashLoanFeeResponse {
+    pub fee: Decimal,
+    /// Fees collected and not withdrawn yet
+    pub collected: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PermitNonceResponse {
+    pub nonce: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FailedWithdrawalsResponse {
+    pub withdrawals: Vec<FailedWithdrawal>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EscrowResponse {
+    pub escrow: Option<Escrow>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EscrowsResponse {
+    pub escrows: Vec<Escrow>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StreamResponse {
+    pub stream: Option<Stream>,
+    pub streamed: Uint128,
+    pub withdrawable: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MerkleStageResponse {
+    pub stage: u8,
+    pub root: String,
+    pub expiration: Expiration,
+    pub total_amount: Uint128,
+    pub claimed: Uint128,
+    pub reclaimed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AirdropClaimedResponse {
+    pub claimed: bool,
 }
@@ -0,0 +1,191 @@
+use cosmwasm_std::{StdError, Uint128};
+use cw_controllers::{AdminError, HookError};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Admin(#[from] AdminError),
+
+    #[error("{0}")]
+    Hook(#[from] HookError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Cannot set to own account")]
+    CannotSetOwnAccount {},
+
+    #[error("Invalid zero amount")]
+    InvalidZeroAmount {},
+
+    #[error("Allowance is expired")]
+    Expired {},
+
+    #[error("No allowance for this account")]
+    NoAllowance {},
+
+    #[error("Minting cannot exceed the cap")]
+    CannotExceedCap {},
+
+    #[error("Logo binary data exceeds 5KB limit")]
+    LogoTooBig {},
+
+    #[error("Invalid xml preamble for SVG")]
+    InvalidXmlPreamble {},
+
+    #[error("Invalid png header")]
+    InvalidPngHeader {},
+
+    #[error("Sender {address} is frozen")]
+    FrozenSender { address: String },
+
+    #[error("Recipient {address} is frozen")]
+    FrozenRecipient { address: String },
+
+    #[error("No recovery address configured for seizures")]
+    NoRecoveryAddress {},
+
+    #[error("Invalid permit signature")]
+    InvalidSignature {},
+
+    #[error("Invalid permit nonce: expected {expected}, got {got}")]
+    InvalidNonce { expected: u64, got: u64 },
+
+    #[error("Fee cannot exceed 100%")]
+    InvalidFee {},
+
+    #[error("Insufficient {denom} reserves: {available} available")]
+    InsufficientReserves { denom: String, available: Uint128 },
+
+    #[error("A flash loan is in progress")]
+    FlashLoanInProgress {},
+
+    #[error("No flash loan in progress")]
+    NoFlashLoan {},
+
+    #[error("Flash loan not repaid: expected balance {expected}, got {actual}")]
+    FlashLoanNotRepaid { expected: Uint128, actual: Uint128 },
+
+    #[error("No {denom} flash loan fees to withdraw")]
+    NoFlashLoanFees { denom: String },
+
+    #[error("Invalid vesting schedule")]
+    InvalidVestingSchedule {},
+
+    #[error("Account {address} already has an unvested schedule")]
+    VestingExists { address: String },
+
+    #[error("Cannot move locked tokens: {locked} still vesting")]
+    LockedTokens { locked: Uint128 },
+
+    #[error("Staking mode is not enabled")]
+    StakingDisabled {},
+
+    #[error("Cannot change the bond denom while coins are staked")]
+    BondDenomInUse {},
+
+    #[error("No validators to delegate to")]
+    NoValidators {},
+
+    #[error("Unknown validator {validator}")]
+    UnknownValidator { validator: String },
+
+    #[error("Validator {validator} is already in the set")]
+    ValidatorExists { validator: String },
+
+    #[error("The staking pool only holds {available}")]
+    InsufficientStake { available: Uint128 },
+
+    #[error("No claims have matured")]
+    NothingToClaim {},
+
+    #[error("A protocol fee needs a fee recipient")]
+    NoFeeRecipient {},
+
+    #[error("Only supports channel with ibc version ics20-1, got {version}")]
+    InvalidIbcVersion { version: String },
+
+    #[error("Only supports unordered channels")]
+    InvalidIbcOrder {},
+
+    #[error("ICS-20 channels cannot be closed")]
+    CannotCloseChannel {},
+
+    #[error("Unknown channel {id}")]
+    UnknownChannel { id: String },
+
+    #[error("Only the wrapped token can be received, got {denom}")]
+    InvalidIbcDenom { denom: String },
+
+    #[error("Only {available} was sent over channel {channel}")]
+    InsufficientChannelBalance { channel: String, available: Uint128 },
+
+    #[error("Batch cannot be empty")]
+    EmptyBatch {},
+
+    #[error("The bond denom is unbonded one withdrawal at a time")]
+    BatchUnbond {},
+
+    #[error("No open escrow {id}")]
+    UnknownEscrow { id: u64 },
+
+    #[error("Escrow {id} has not expired yet")]
+    EscrowNotExpired { id: u64 },
+
+    #[error("A stream must end after it starts, and in the future")]
+    InvalidStream {},
+
+    #[error("No open stream {id}")]
+    UnknownStream { id: u64 },
+
+    #[error("Airdrop stage {stage} already exists")]
+    StageExists { stage: u8 },
+
+    #[error("No airdrop stage {stage}")]
+    UnknownStage { stage: u8 },
+
+    #[error("Airdrop stage {stage} has not expired yet")]
+    StageNotExpired { stage: u8 },
+
+    #[error("Merkle root must be a hex encoded SHA-256 hash")]
+    InvalidMerkleRoot {},
+
+    #[error("Invalid Merkle proof")]
+    InvalidProof {},
+
+    #[error("Airdrop of stage {stage} already claimed")]
+    AirdropClaimed { stage: u8 },
+
+    #[error("Airdrop stage {stage} is exhausted")]
+    AirdropExhausted { stage: u8 },
+
+    #[error("No failed withdrawal {id}")]
+    UnknownFailedWithdrawal { id: u64 },
+
+    #[error("Unknown reply id {id}")]
+    UnknownReplyId { id: u64 },
+}
+
+/// cw20-base errors are flattened so callers see the same variant whether the
+/// check happened in cw20-base or in this contract.
+impl From<cw20_base::ContractError> for ContractError {
+    fn from(err: cw20_base::ContractError) -> Self {
+        use cw20_base::ContractError as Base;
+        match err {
+            Base::Std(err) => ContractError::Std(err),
+            Base::Unauthorized {} => ContractError::Unauthorized {},
+            Base::CannotSetOwnAccount {} => ContractError::CannotSetOwnAccount {},
+            Base::InvalidZeroAmount {} => ContractError::InvalidZeroAmount {},
+            Base::Expired {} => ContractError::Expired {},
+            Base::NoAllowance {} => ContractError::NoAllowance {},
+            Base::CannotExceedCap {} => ContractError::CannotExceedCap {},
+            Base::LogoTooBig {} => ContractError::LogoTooBig {},
+            Base::InvalidXmlPreamble {} => ContractError::InvalidXmlPreamble {},
+            Base::InvalidPngHeader {} => ContractError::InvalidPngHeader {},
+        }
+    }
+}
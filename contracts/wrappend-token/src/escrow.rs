@@ -0,0 +1,191 @@
+use cosmwasm_std::{Addr, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult, Uint128};
+use cw0::Expiration;
+use cw_storage_plus::{Bound, U64Key};
+
+use crate::compliance::{assert_recipient_not_frozen, assert_sender_not_frozen};
+use crate::erc20::{EscrowResponse, EscrowsResponse};
+use crate::error::ContractError;
+use crate::hooks::{add_balance_hooks, TokenBalances};
+use crate::ledger::move_token_balance;
+use crate::state::{escrows, Escrow, ESCROW_SEQ};
+use crate::vesting::assert_vested;
+
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+/// Moves amount tokens of the sender into the contract until the arbiter approves
+/// or the escrow expires
+pub fn execute_create_escrow(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: String,
+    arbiter: String,
+    amount: Uint128,
+    expiration: Expiration,
+) -> Result<Response, ContractError> {
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    if expiration.is_expired(&env.block) {
+        return Err(ContractError::Expired {});
+    }
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let arbiter = deps.api.addr_validate(&arbiter)?;
+    assert_sender_not_frozen(deps.storage, &info.sender)?;
+    assert_recipient_not_frozen(deps.storage, &recipient)?;
+    assert_vested(deps.storage, &env.block, &info.sender, amount)?;
+
+    let contract = env.contract.address;
+    let before = TokenBalances::load(deps.storage, &[&info.sender, &contract])?;
+    move_token_balance(
+        deps.storage,
+        env.block.height,
+        &info.sender,
+        &contract,
+        amount,
+    )?;
+
+    let id = ESCROW_SEQ.may_load(deps.storage)?.unwrap_or_default() + 1;
+    ESCROW_SEQ.save(deps.storage, &id)?;
+    let escrow = Escrow {
+        id,
+        sender: info.sender,
+        recipient,
+        arbiter,
+        amount,
+        expiration,
+    };
+    escrows().save(deps.storage, U64Key::new(id), &escrow)?;
+
+    let res = Response::new()
+        .add_attribute("action", "create_escrow")
+        .add_attribute("id", id.to_string())
+        .add_attribute("from", escrow.sender)
+        .add_attribute("to", escrow.recipient)
+        .add_attribute("arbiter", escrow.arbiter)
+        .add_attribute("amount", amount);
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+/// Arbiter only. Pays the escrow to the recipient, unless it has expired
+pub fn execute_approve_escrow(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let escrow = load_escrow(deps.as_ref(), id)?;
+    if info.sender != escrow.arbiter {
+        return Err(ContractError::Unauthorized {});
+    }
+    if escrow.expiration.is_expired(&env.block) {
+        return Err(ContractError::Expired {});
+    }
+    assert_recipient_not_frozen(deps.storage, &escrow.recipient)?;
+
+    let res = Response::new()
+        .add_attribute("action", "approve_escrow")
+        .add_attribute("id", id.to_string())
+        .add_attribute("to", escrow.recipient.as_str());
+    let to = escrow.recipient.clone();
+    release(deps, env, escrow, &to, res)
+}
+
+/// Returns an expired escrow to its sender. Anyone may call it.
+pub fn execute_refund_escrow(deps: DepsMut, env: Env, id: u64) -> Result<Response, ContractError> {
+    let escrow = load_escrow(deps.as_ref(), id)?;
+    if !escrow.expiration.is_expired(&env.block) {
+        return Err(ContractError::EscrowNotExpired { id });
+    }
+    assert_recipient_not_frozen(deps.storage, &escrow.sender)?;
+
+    let res = Response::new()
+        .add_attribute("action", "refund_escrow")
+        .add_attribute("id", id.to_string())
+        .add_attribute("to", escrow.sender.as_str());
+    let to = escrow.sender.clone();
+    release(deps, env, escrow, &to, res)
+}
+
+fn load_escrow(deps: Deps, id: u64) -> Result<Escrow, ContractError> {
+    escrows()
+        .may_load(deps.storage, U64Key::new(id))?
+        .ok_or(ContractError::UnknownEscrow { id })
+}
+
+/// Closes the escrow, paying its tokens out of the contract
+fn release(
+    deps: DepsMut,
+    env: Env,
+    escrow: Escrow,
+    to: &Addr,
+    res: Response,
+) -> Result<Response, ContractError> {
+    escrows().remove(deps.storage, U64Key::new(escrow.id))?;
+    let contract = env.contract.address;
+    let before = TokenBalances::load(deps.storage, &[&contract, to])?;
+    move_token_balance(deps.storage, env.block.height, &contract, to, escrow.amount)?;
+
+    let res = res.add_attribute("amount", escrow.amount);
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+pub fn query_escrow(deps: Deps, id: u64) -> StdResult<EscrowResponse> {
+    let escrow = escrows().may_load(deps.storage, U64Key::new(id))?;
+    Ok(EscrowResponse { escrow })
+}
+
+/// Open escrows of the sender, ordered by id
+pub fn query_escrows_by_sender(
+    deps: Deps,
+    sender: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<EscrowsResponse> {
+    let sender = deps.api.addr_validate(&sender)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+
+    let list: StdResult<Vec<_>> = escrows()
+        .idx
+        .sender
+        .prefix(sender.as_bytes().to_vec())
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, escrow)| escrow))
+        .collect();
+
+    Ok(EscrowsResponse { escrows: list? })
+}
+
+/// Open escrows paying the recipient, ordered by id
+pub fn query_escrows_by_recipient(
+    deps: Deps,
+    recipient: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<EscrowsResponse> {
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+
+    let list: StdResult<Vec<_>> = escrows()
+        .idx
+        .recipient
+        .prefix(recipient.as_bytes().to_vec())
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, escrow)| escrow))
+        .collect();
+
+    Ok(EscrowsResponse { escrows: list? })
+}
@@ -0,0 +1,183 @@
+use cosmwasm_std::{
+    to_binary, BankMsg, Binary, Coin, Decimal, Deps, DepsMut, Env, MessageInfo, Order, Response,
+    StdResult, Storage, Uint128, WasmMsg,
+};
+
+use crate::contract::is_white_listed_denom;
+use crate::erc20::{ExecuteMsg, FlashLoanFeeResponse};
+use crate::error::ContractError;
+use crate::state::{FlashLoanState, ADMIN, FLASH_LOAN, FLASH_LOAN_FEE, FLASH_LOAN_FEES};
+
+/// Deposits and withdrawals would let a borrower repay with the loaned coins
+/// themselves, so they are blocked until the loan is settled.
+pub fn assert_no_flash_loan(storage: &dyn Storage) -> Result<(), ContractError> {
+    if FLASH_LOAN.may_load(storage)?.is_some() {
+        return Err(ContractError::FlashLoanInProgress {});
+    }
+    Ok(())
+}
+
+pub fn execute_update_flash_loan_fee(
+    deps: DepsMut,
+    info: MessageInfo,
+    fee: Decimal,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if fee > Decimal::one() {
+        return Err(ContractError::InvalidFee {});
+    }
+    FLASH_LOAN_FEE.save(deps.storage, &fee)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_flash_loan_fee")
+        .add_attribute("fee", fee.to_string()))
+}
+
+/// Lends the coins to the callback contract and schedules FinishFlashLoan right
+/// after its callback, so a shortfall reverts the whole transaction.
+pub fn execute_flash_loan(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    denom: String,
+    amount: Uint128,
+    callback_contract: String,
+    msg: Binary,
+) -> Result<Response, ContractError> {
+    assert_no_flash_loan(deps.storage)?;
+    if amount == Uint128::zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    is_white_listed_denom(deps.as_ref(), &denom)?;
+    let borrower = deps.api.addr_validate(&callback_contract)?;
+
+    let reserve = deps
+        .querier
+        .query_balance(&env.contract.address, &denom)?
+        .amount;
+    // booked fees belong to the admin and are not lent out
+    let booked = FLASH_LOAN_FEES
+        .may_load(deps.storage, &denom)?
+        .unwrap_or_default();
+    let available = reserve.saturating_sub(booked);
+    if available < amount {
+        return Err(ContractError::InsufficientReserves { denom, available });
+    }
+    let fee = amount * FLASH_LOAN_FEE.may_load(deps.storage)?.unwrap_or_default();
+    FLASH_LOAN.save(
+        deps.storage,
+        &FlashLoanState {
+            denom: denom.clone(),
+            expected_balance: reserve + fee,
+            fee,
+        },
+    )?;
+
+    let callback = WasmMsg::Execute {
+        contract_addr: borrower.to_string(),
+        msg,
+        funds: vec![Coin {
+            denom: denom.clone(),
+            amount,
+        }],
+    };
+    let finish = WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        msg: to_binary(&ExecuteMsg::FinishFlashLoan {})?,
+        funds: vec![],
+    };
+
+    Ok(Response::new()
+        .add_message(callback)
+        .add_message(finish)
+        .add_attribute("action", "flash_loan")
+        .add_attribute("borrower", borrower)
+        .add_attribute("denom", denom)
+        .add_attribute("amount", amount)
+        .add_attribute("fee", fee))
+}
+
+pub fn execute_finish_flash_loan(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+    let loan = FLASH_LOAN
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoFlashLoan {})?;
+
+    let balance = deps
+        .querier
+        .query_balance(&env.contract.address, &loan.denom)?
+        .amount;
+    if balance < loan.expected_balance {
+        return Err(ContractError::FlashLoanNotRepaid {
+            expected: loan.expected_balance,
+            actual: balance,
+        });
+    }
+    FLASH_LOAN.remove(deps.storage);
+    // the fee is not part of any deposit, so it is booked apart until the admin
+    // withdraws it
+    FLASH_LOAN_FEES.update(deps.storage, &loan.denom, |fees| -> StdResult<_> {
+        Ok(fees.unwrap_or_default() + loan.fee)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "finish_flash_loan")
+        .add_attribute("denom", loan.denom)
+        .add_attribute("balance", balance)
+        .add_attribute("fee", loan.fee))
+}
+
+pub fn execute_withdraw_flash_loan_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    assert_no_flash_loan(deps.storage)?;
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender,
+    };
+    let amount = FLASH_LOAN_FEES
+        .may_load(deps.storage, &denom)?
+        .unwrap_or_default();
+    if amount.is_zero() {
+        return Err(ContractError::NoFlashLoanFees { denom });
+    }
+    FLASH_LOAN_FEES.remove(deps.storage, &denom);
+
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin {
+                denom: denom.clone(),
+                amount,
+            }],
+        })
+        .add_attribute("action", "withdraw_flash_loan_fees")
+        .add_attribute("recipient", recipient)
+        .add_attribute("denom", denom)
+        .add_attribute("amount", amount))
+}
+
+pub fn query_flash_loan_fee(deps: Deps) -> StdResult<FlashLoanFeeResponse> {
+    let fee = FLASH_LOAN_FEE.may_load(deps.storage)?.unwrap_or_default();
+    let collected = FLASH_LOAN_FEES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (denom, amount) = item?;
+            Ok(Coin {
+                denom: String::from_utf8(denom)?,
+                amount,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(FlashLoanFeeResponse { fee, collected })
+}
@@ -0,0 +1,104 @@
+use cosmwasm_std::{
+    to_binary, Addr, Binary, CosmosMsg, Response, StdResult, Storage, SubMsg, Uint128, WasmMsg,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::ledger::token_balance;
+use crate::state::HOOKS;
+
+/// A single balance change. The denom is set for deposited native balances and
+/// unset for the wrapped token itself.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BalanceDiff {
+    pub address: String,
+    pub denom: Option<String>,
+    pub old: Uint128,
+    pub new: Uint128,
+}
+
+/// Sent to every hook contract after balances change
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BalanceChangedHookMsg {
+    pub diffs: Vec<BalanceDiff>,
+}
+
+impl BalanceChangedHookMsg {
+    /// serializes the message
+    pub fn into_binary(self) -> StdResult<Binary> {
+        let msg = BalanceChangedExecuteMsg::BalanceChangedHook(self);
+        to_binary(&msg)
+    }
+
+    /// creates a cosmos_msg sending this struct to the named contract
+    pub fn into_cosmos_msg<T: Into<String>>(self, contract_addr: T) -> StdResult<CosmosMsg> {
+        let msg = self.into_binary()?;
+        let execute = WasmMsg::Execute {
+            contract_addr: contract_addr.into(),
+            msg,
+            funds: vec![],
+        };
+        Ok(execute.into())
+    }
+}
+
+// This is just a helper to properly serialize the above message
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum BalanceChangedExecuteMsg {
+    BalanceChangedHook(BalanceChangedHookMsg),
+}
+
+/// Wrapped token balances of some accounts, taken before a change so the
+/// diffs can be built once it is applied
+pub struct TokenBalances(Vec<(Addr, Uint128)>);
+
+impl TokenBalances {
+    pub fn load(storage: &dyn Storage, addresses: &[&Addr]) -> StdResult<Self> {
+        let mut balances: Vec<(Addr, Uint128)> = vec![];
+        for address in addresses {
+            if balances.iter().any(|(addr, _)| addr == *address) {
+                continue;
+            }
+            let balance = token_balance(storage, address)?;
+            balances.push(((*address).clone(), balance));
+        }
+        Ok(TokenBalances(balances))
+    }
+
+    pub fn diffs(self, storage: &dyn Storage) -> StdResult<Vec<BalanceDiff>> {
+        self.0
+            .into_iter()
+            .map(|(address, old)| {
+                let new = token_balance(storage, &address)?;
+                Ok(BalanceDiff {
+                    address: address.into(),
+                    denom: None,
+                    old,
+                    new,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Notifications for every registered hook, none if nothing changed
+pub fn balance_hook_msgs(storage: &dyn Storage, diffs: Vec<BalanceDiff>) -> StdResult<Vec<SubMsg>> {
+    let diffs: Vec<BalanceDiff> = diffs.into_iter().filter(|d| d.old != d.new).collect();
+    if diffs.is_empty() {
+        return Ok(vec![]);
+    }
+    let msg = BalanceChangedHookMsg { diffs };
+    HOOKS.prepare_hooks(storage, |hook| {
+        msg.clone().into_cosmos_msg(hook).map(SubMsg::new)
+    })
+}
+
+/// Appends a notification to every registered hook, unless nothing changed
+pub fn add_balance_hooks(
+    storage: &dyn Storage,
+    res: Response,
+    diffs: Vec<BalanceDiff>,
+) -> StdResult<Response> {
+    Ok(res.add_submessages(balance_hook_msgs(storage, diffs)?))
+}
@@ -0,0 +1,333 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    from_binary, to_binary, Binary, Deps, DepsMut, Env, IbcBasicResponse, IbcChannel,
+    IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcMsg, IbcOrder, IbcPacket,
+    IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, IbcTimeout,
+    MessageInfo, Order, Response, StdResult, Storage, SubMsg, Uint128,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::compliance::{assert_recipient_not_frozen, assert_sender_not_frozen};
+use crate::erc20::{ChannelResponse, ListChannelsResponse};
+use crate::error::ContractError;
+use crate::hooks::{add_balance_hooks, balance_hook_msgs, TokenBalances};
+use crate::ledger::{decrease_token_balance, increase_token_balance};
+use crate::state::{ChannelInfo, CHANNEL_BALANCES, CHANNEL_INFO};
+use crate::vesting::assert_vested;
+
+pub const ICS20_VERSION: &str = "ics20-1";
+pub const ICS20_ORDERING: IbcOrder = IbcOrder::Unordered;
+
+/// Seconds before a transfer times out when the sender does not say
+pub const DEFAULT_IBC_TIMEOUT: u64 = 600;
+
+// Tokens sent out are taken off the sender and held in the escrow of their
+// channel, CHANNEL_BALANCES, apart from the token balance of the contract that
+// escrows and streams use. They only leave it when they come back over the same
+// channel or the transfer is refunded.
+
+/// The ICS-20 FungibleTokenPacketData
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Ics20Packet {
+    /// The wrapped token is `cw20:<contract address>` on this chain, prefixed
+    /// with the port and channel of each hop on the way back
+    pub denom: String,
+    pub amount: Uint128,
+    pub sender: String,
+    pub receiver: String,
+}
+
+/// The ICS-20 acknowledgement, either a result or an error
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Ics20Ack {
+    Result(Binary),
+    Error(String),
+}
+
+fn ack_success() -> Binary {
+    // the ICS-20 success result is a single byte 0x01
+    to_binary(&Ics20Ack::Result(Binary::from(vec![1]))).unwrap()
+}
+
+fn ack_fail(err: String) -> Binary {
+    to_binary(&Ics20Ack::Error(err)).unwrap()
+}
+
+fn native_denom(env: &Env) -> String {
+    format!("cw20:{}", env.contract.address)
+}
+
+fn enforce_order_and_version(
+    channel: &IbcChannel,
+    counterparty_version: Option<&str>,
+) -> Result<(), ContractError> {
+    if channel.version != ICS20_VERSION {
+        return Err(ContractError::InvalidIbcVersion {
+            version: channel.version.clone(),
+        });
+    }
+    if let Some(version) = counterparty_version {
+        if version != ICS20_VERSION {
+            return Err(ContractError::InvalidIbcVersion {
+                version: version.to_string(),
+            });
+        }
+    }
+    if channel.order != ICS20_ORDERING {
+        return Err(ContractError::InvalidIbcOrder {});
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_open(
+    _deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelOpenMsg,
+) -> Result<(), ContractError> {
+    enforce_order_and_version(msg.channel(), msg.counterparty_version())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_connect(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelConnectMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let channel = msg.channel();
+    enforce_order_and_version(channel, msg.counterparty_version())?;
+    let info = ChannelInfo {
+        id: channel.endpoint.channel_id.clone(),
+        counterparty_endpoint: channel.counterparty_endpoint.clone(),
+        connection_id: channel.connection_id.clone(),
+    };
+    CHANNEL_INFO.save(deps.storage, &info.id, &info)?;
+
+    Ok(IbcBasicResponse::new()
+        .add_attribute("action", "ibc_connect")
+        .add_attribute("channel", info.id))
+}
+
+/// Escrowed tokens would be stuck, so channels are never closed
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_close(
+    _deps: DepsMut,
+    _env: Env,
+    _msg: IbcChannelCloseMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    Err(ContractError::CannotCloseChannel {})
+}
+
+/// Releases tokens coming back from the remote chain. Failures are reported
+/// in an error acknowledgement rather than by failing the transaction.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_receive(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketReceiveMsg,
+) -> Result<IbcReceiveResponse, ContractError> {
+    let packet = msg.packet;
+    let res = match do_ibc_packet_receive(deps, &env, &packet) {
+        Ok((msg, messages)) => IbcReceiveResponse::new()
+            .set_ack(ack_success())
+            .add_submessages(messages)
+            .add_attribute("action", "ibc_receive")
+            .add_attribute("sender", msg.sender)
+            .add_attribute("receiver", msg.receiver)
+            .add_attribute("amount", msg.amount)
+            .add_attribute("success", "true"),
+        Err(err) => IbcReceiveResponse::new()
+            .set_ack(ack_fail(err.to_string()))
+            .add_attribute("action", "ibc_receive")
+            .add_attribute("success", "false")
+            .add_attribute("error", err.to_string()),
+    };
+    Ok(res)
+}
+
+// Everything is checked before the first write, as an error does not revert
+fn do_ibc_packet_receive(
+    deps: DepsMut,
+    env: &Env,
+    packet: &IbcPacket,
+) -> Result<(Ics20Packet, Vec<SubMsg>), ContractError> {
+    let msg: Ics20Packet = from_binary(&packet.data)?;
+    let channel = packet.dest.channel_id.as_str();
+
+    // only our own token coming back is accepted, prefixed by the sending end
+    let expected = format!(
+        "{}/{}/{}",
+        packet.src.port_id,
+        packet.src.channel_id,
+        native_denom(env)
+    );
+    if msg.denom != expected {
+        return Err(ContractError::InvalidIbcDenom { denom: msg.denom });
+    }
+    let receiver = deps.api.addr_validate(&msg.receiver)?;
+    assert_recipient_not_frozen(deps.storage, &receiver)?;
+    let left = channel_balance_after(deps.storage, channel, msg.amount)?;
+
+    let before = TokenBalances::load(deps.storage, &[&receiver])?;
+    increase_token_balance(deps.storage, env.block.height, &receiver, msg.amount)?;
+    CHANNEL_BALANCES.save(deps.storage, channel, &left)?;
+    let messages = balance_hook_msgs(deps.storage, before.diffs(deps.storage)?)?;
+    Ok((msg, messages))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_ack(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketAckMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let ack: Ics20Ack = from_binary(&msg.acknowledgement.data)?;
+    match ack {
+        Ics20Ack::Result(_) => {
+            // the tokens stay escrowed for the channel until they come back
+            let packet: Ics20Packet = from_binary(&msg.original_packet.data)?;
+            Ok(IbcBasicResponse::new()
+                .add_attribute("action", "ibc_ack")
+                .add_attribute("sender", packet.sender)
+                .add_attribute("amount", packet.amount)
+                .add_attribute("success", "true"))
+        }
+        Ics20Ack::Error(err) => refund_packet(deps, &env, &msg.original_packet).map(|res| {
+            res.add_attribute("action", "ibc_ack")
+                .add_attribute("error", err)
+        }),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_timeout(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketTimeoutMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    refund_packet(deps, &env, &msg.packet).map(|res| res.add_attribute("action", "ibc_timeout"))
+}
+
+/// Returns the escrowed tokens of a failed transfer to the sender
+fn refund_packet(
+    deps: DepsMut,
+    env: &Env,
+    packet: &IbcPacket,
+) -> Result<IbcBasicResponse, ContractError> {
+    let msg: Ics20Packet = from_binary(&packet.data)?;
+    let sender = deps.api.addr_validate(&msg.sender)?;
+    let channel = packet.src.channel_id.as_str();
+    let left = channel_balance_after(deps.storage, channel, msg.amount)?;
+
+    let before = TokenBalances::load(deps.storage, &[&sender])?;
+    increase_token_balance(deps.storage, env.block.height, &sender, msg.amount)?;
+    CHANNEL_BALANCES.save(deps.storage, channel, &left)?;
+    let messages = balance_hook_msgs(deps.storage, before.diffs(deps.storage)?)?;
+
+    Ok(IbcBasicResponse::new()
+        .add_submessages(messages)
+        .add_attribute("sender", msg.sender)
+        .add_attribute("amount", msg.amount)
+        .add_attribute("success", "false"))
+}
+
+/// Moves the tokens to the escrow of the channel and sends an ICS-20 packet over it
+pub fn execute_ibc_transfer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    channel: String,
+    remote_address: String,
+    amount: Uint128,
+    timeout: Option<u64>,
+) -> Result<Response, ContractError> {
+    if amount == Uint128::zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    if !CHANNEL_INFO.has(deps.storage, &channel) {
+        return Err(ContractError::UnknownChannel { id: channel });
+    }
+    assert_sender_not_frozen(deps.storage, &info.sender)?;
+    assert_vested(deps.storage, &env.block, &info.sender, amount)?;
+
+    let before = TokenBalances::load(deps.storage, &[&info.sender])?;
+    decrease_token_balance(deps.storage, env.block.height, &info.sender, amount)?;
+    increase_channel_balance(deps.storage, &channel, amount)?;
+
+    let packet = Ics20Packet {
+        denom: native_denom(&env),
+        amount,
+        sender: info.sender.to_string(),
+        receiver: remote_address.clone(),
+    };
+    let timeout = env
+        .block
+        .time
+        .plus_seconds(timeout.unwrap_or(DEFAULT_IBC_TIMEOUT));
+    let msg = IbcMsg::SendPacket {
+        channel_id: channel.clone(),
+        data: to_binary(&packet)?,
+        timeout: IbcTimeout::with_timestamp(timeout),
+    };
+
+    let res = Response::new()
+        .add_message(msg)
+        .add_attribute("action", "ibc_transfer")
+        .add_attribute("from", info.sender)
+        .add_attribute("to", remote_address)
+        .add_attribute("channel", channel)
+        .add_attribute("amount", amount);
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+/// What the channel escrow holds once amount is released from it, failing if it
+/// holds less
+fn channel_balance_after(
+    storage: &dyn Storage,
+    channel: &str,
+    amount: Uint128,
+) -> Result<Uint128, ContractError> {
+    let outstanding = CHANNEL_BALANCES
+        .may_load(storage, channel)?
+        .unwrap_or_default();
+    if outstanding < amount {
+        return Err(ContractError::InsufficientChannelBalance {
+            channel: channel.to_string(),
+            available: outstanding,
+        });
+    }
+    Ok(outstanding - amount)
+}
+
+fn increase_channel_balance(
+    storage: &mut dyn Storage,
+    channel: &str,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    CHANNEL_BALANCES.update(storage, channel, |balance| -> StdResult<_> {
+        Ok(balance.unwrap_or_default().checked_add(amount)?)
+    })
+}
+
+pub fn query_channel(deps: Deps, id: String) -> StdResult<ChannelResponse> {
+    let info = CHANNEL_INFO.load(deps.storage, &id)?;
+    let balance = CHANNEL_BALANCES
+        .may_load(deps.storage, &id)?
+        .unwrap_or_default();
+    Ok(ChannelResponse { info, balance })
+}
+
+pub fn query_list_channels(deps: Deps) -> StdResult<ListChannelsResponse> {
+    let channels = CHANNEL_INFO
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, info)| info))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(ListChannelsResponse { channels })
+}
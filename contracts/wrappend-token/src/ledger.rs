@@ -0,0 +1,208 @@
+use cosmwasm_std::{Addr, StdError, StdResult, Storage, Uint128};
+use cw20_base::state::TOKEN_INFO;
+
+use crate::error::ContractError;
+use crate::state::{BALANCES, TOKEN_BALANCES, TOTAL_SUPPLY, TOTAL_SUPPLY_KEY};
+
+// Every write goes through the snapshot maps with the current block height, so
+// historical balances and supply can be queried with the *_at_height helpers.
+// Those return the value at the beginning of the given block.
+
+pub fn token_balance(storage: &dyn Storage, address: &Addr) -> StdResult<Uint128> {
+    Ok(TOKEN_BALANCES
+        .may_load(storage, address)?
+        .unwrap_or_default())
+}
+
+pub fn token_balance_at_height(
+    storage: &dyn Storage,
+    address: &Addr,
+    height: u64,
+) -> StdResult<Uint128> {
+    Ok(TOKEN_BALANCES
+        .may_load_at_height(storage, address, height)?
+        .unwrap_or_default())
+}
+
+pub fn increase_token_balance(
+    storage: &mut dyn Storage,
+    height: u64,
+    address: &Addr,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    TOKEN_BALANCES.update(
+        storage,
+        address,
+        height,
+        |balance: Option<Uint128>| -> StdResult<_> {
+            Ok(balance.unwrap_or_default().checked_add(amount)?)
+        },
+    )
+}
+
+pub fn decrease_token_balance(
+    storage: &mut dyn Storage,
+    height: u64,
+    address: &Addr,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    TOKEN_BALANCES.update(
+        storage,
+        address,
+        height,
+        |balance: Option<Uint128>| -> StdResult<_> {
+            Ok(balance.unwrap_or_default().checked_sub(amount)?)
+        },
+    )
+}
+
+pub fn move_token_balance(
+    storage: &mut dyn Storage,
+    height: u64,
+    from: &Addr,
+    to: &Addr,
+    amount: Uint128,
+) -> StdResult<()> {
+    decrease_token_balance(storage, height, from, amount)?;
+    increase_token_balance(storage, height, to, amount)?;
+    Ok(())
+}
+
+pub fn total_supply(storage: &dyn Storage) -> StdResult<Uint128> {
+    Ok(TOTAL_SUPPLY
+        .may_load(storage, TOTAL_SUPPLY_KEY)?
+        .unwrap_or_default())
+}
+
+pub fn total_supply_at_height(storage: &dyn Storage, height: u64) -> StdResult<Uint128> {
+    Ok(TOTAL_SUPPLY
+        .may_load_at_height(storage, TOTAL_SUPPLY_KEY, height)?
+        .unwrap_or_default())
+}
+
+pub fn increase_total_supply(
+    storage: &mut dyn Storage,
+    height: u64,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    let supply = TOTAL_SUPPLY.update(
+        storage,
+        TOTAL_SUPPLY_KEY,
+        height,
+        |supply: Option<Uint128>| -> StdResult<_> {
+            Ok(supply.unwrap_or_default().checked_add(amount)?)
+        },
+    )?;
+    sync_token_info(storage, supply)?;
+    Ok(supply)
+}
+
+pub fn decrease_total_supply(
+    storage: &mut dyn Storage,
+    height: u64,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    let supply = TOTAL_SUPPLY.update(
+        storage,
+        TOTAL_SUPPLY_KEY,
+        height,
+        |supply: Option<Uint128>| -> StdResult<_> {
+            Ok(supply.unwrap_or_default().checked_sub(amount)?)
+        },
+    )?;
+    sync_token_info(storage, supply)?;
+    Ok(supply)
+}
+
+/// Keeps the total_supply of the cw20-base TOKEN_INFO equal to the snapshotted
+/// supply, for anything reading it directly
+fn sync_token_info(storage: &mut dyn Storage, supply: Uint128) -> StdResult<()> {
+    TOKEN_INFO.update(storage, |mut info| -> StdResult<_> {
+        info.total_supply = supply;
+        Ok(info)
+    })?;
+    Ok(())
+}
+
+/// Seeds the snapshotted supply from the cw20-base TOKEN_INFO of a contract
+/// instantiated before the supply was snapshotted. A no-op once it is set.
+pub fn seed_total_supply(storage: &mut dyn Storage, height: u64) -> StdResult<()> {
+    if TOTAL_SUPPLY.may_load(storage, TOTAL_SUPPLY_KEY)?.is_some() {
+        return Ok(());
+    }
+    let supply = TOKEN_INFO.load(storage)?.total_supply;
+    TOTAL_SUPPLY.save(storage, TOTAL_SUPPLY_KEY, &supply, height)
+}
+
+pub fn denom_balance(storage: &dyn Storage, address: &Addr, denom: &str) -> StdResult<Uint128> {
+    Ok(BALANCES
+        .may_load(storage, (address, denom))?
+        .unwrap_or_default())
+}
+
+pub fn denom_balance_at_height(
+    storage: &dyn Storage,
+    address: &Addr,
+    denom: &str,
+    height: u64,
+) -> StdResult<Uint128> {
+    Ok(BALANCES
+        .may_load_at_height(storage, (address, denom), height)?
+        .unwrap_or_default())
+}
+
+pub fn increase_denom_balance(
+    storage: &mut dyn Storage,
+    height: u64,
+    address: &Addr,
+    denom: &str,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    BALANCES.update(
+        storage,
+        (address, denom),
+        height,
+        |balance: Option<Uint128>| -> StdResult<_> {
+            Ok(balance.unwrap_or_default().checked_add(amount)?)
+        },
+    )
+}
+
+pub fn decrease_denom_balance(
+    storage: &mut dyn Storage,
+    height: u64,
+    address: &Addr,
+    denom: &str,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    BALANCES.update(
+        storage,
+        (address, denom),
+        height,
+        |balance: Option<Uint128>| -> StdResult<_> {
+            Ok(balance.unwrap_or_default().checked_sub(amount)?)
+        },
+    )
+}
+
+/// Creates amount new tokens for the recipient, up to the cap of the token. The
+/// caller checks who may mint.
+pub fn mint_tokens(
+    storage: &mut dyn Storage,
+    height: u64,
+    recipient: &Addr,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    let config = TOKEN_INFO.load(storage)?;
+    let supply = total_supply(storage)?
+        .checked_add(amount)
+        .map_err(StdError::from)?;
+    if let Some(limit) = config.get_cap() {
+        if supply > limit {
+            return Err(ContractError::CannotExceedCap {});
+        }
+    }
+    increase_total_supply(storage, height, amount)?;
+    increase_token_balance(storage, height, recipient, amount)?;
+    Ok(())
+}
@@ -1,6 +1,21 @@
 pub mod contract;
+mod airdrop;
+mod compliance;
+mod error;
+mod escrow;
+mod flash_loan;
+pub mod hooks;
+pub mod ibc;
+mod ledger;
+pub mod permit;
+mod staking;
 mod state;
+mod stream;
 pub mod erc20;
+pub mod vesting;
+mod withdrawals;
+
+pub use crate::error::ContractError;
 
 #[cfg(test)]
 mod tests;
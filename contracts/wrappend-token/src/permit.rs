@@ -0,0 +1,116 @@
+use cosmwasm_std::{
+    to_vec, Addr, Binary, CanonicalAddr, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+    Uint128,
+};
+use cw20::{AllowanceResponse, Expiration};
+use cw20_base::state::ALLOWANCES;
+use ripemd160::Ripemd160;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::erc20::PermitNonceResponse;
+use crate::error::ContractError;
+use crate::state::PERMIT_NONCES;
+
+/// The message an owner signs to grant an allowance off-chain. The chain id and
+/// contract address bind the signature to this deployment only.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PermitMsg {
+    pub chain_id: String,
+    pub contract: String,
+    pub spender: String,
+    pub amount: Uint128,
+    pub expires: Expiration,
+    pub nonce: u64,
+}
+
+impl PermitMsg {
+    /// SHA-256 of the JSON encoding, which is what the owner signs
+    pub fn digest(&self) -> StdResult<Vec<u8>> {
+        Ok(Sha256::digest(&to_vec(self)?).to_vec())
+    }
+}
+
+/// Derives the account address of a compressed secp256k1 public key the way the
+/// Cosmos SDK does: RIPEMD-160 over SHA-256 of the key
+pub fn pubkey_to_address(deps: Deps, pubkey: &[u8]) -> StdResult<Addr> {
+    let hash = Ripemd160::digest(&Sha256::digest(pubkey));
+    deps.api
+        .addr_humanize(&CanonicalAddr::from(hash.as_slice()))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_permit(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    owner_pubkey: Binary,
+    spender: String,
+    amount: Uint128,
+    expires: Expiration,
+    nonce: u64,
+    signature: Binary,
+) -> Result<Response, ContractError> {
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::Expired {});
+    }
+    let spender_addr = deps.api.addr_validate(&spender)?;
+
+    let permit = PermitMsg {
+        chain_id: env.block.chain_id.clone(),
+        contract: env.contract.address.to_string(),
+        spender: spender.clone(),
+        amount,
+        expires,
+        nonce,
+    };
+    let verified = deps
+        .api
+        .secp256k1_verify(&permit.digest()?, &signature, &owner_pubkey)
+        .map_err(|_| ContractError::InvalidSignature {})?;
+    if !verified {
+        return Err(ContractError::InvalidSignature {});
+    }
+
+    let owner = pubkey_to_address(deps.as_ref(), &owner_pubkey)?;
+    if owner == spender_addr {
+        return Err(ContractError::CannotSetOwnAccount {});
+    }
+
+    // each nonce is usable once, in order
+    let expected = PERMIT_NONCES
+        .may_load(deps.storage, &owner)?
+        .unwrap_or_default();
+    if nonce != expected {
+        return Err(ContractError::InvalidNonce {
+            expected,
+            got: nonce,
+        });
+    }
+    PERMIT_NONCES.save(deps.storage, &owner, &(expected + 1))?;
+
+    ALLOWANCES.save(
+        deps.storage,
+        (&owner, &spender_addr),
+        &AllowanceResponse {
+            allowance: amount,
+            expires,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "permit")
+        .add_attribute("owner", owner)
+        .add_attribute("spender", spender)
+        .add_attribute("amount", amount)
+        .add_attribute("nonce", nonce.to_string()))
+}
+
+pub fn query_permit_nonce(deps: Deps, owner: String) -> StdResult<PermitNonceResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let nonce = PERMIT_NONCES
+        .may_load(deps.storage, &owner)?
+        .unwrap_or_default();
+    Ok(PermitNonceResponse { nonce })
+}
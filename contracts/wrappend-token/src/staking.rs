@@ -0,0 +1,788 @@
+use cosmwasm_std::{
+    Addr, BankMsg, Coin, CosmosMsg, Decimal, Deps, DepsMut, DistributionMsg, Env, MessageInfo,
+    Order, QuerierWrapper, Response, StakingMsg, StdError, StdResult, Storage, Uint128,
+};
+use cw0::Duration;
+use cw_controllers::ClaimsResponse;
+
+use crate::compliance::assert_sender_not_frozen;
+use crate::erc20::{DelegationInfo, DelegationsResponse, StakingResponse, ValidatorWeight};
+use crate::error::ContractError;
+use crate::flash_loan::assert_no_flash_loan;
+use crate::hooks::{add_balance_hooks, TokenBalances};
+use crate::ledger::{decrease_token_balance, decrease_total_supply, mint_tokens};
+use crate::state::{
+    StakingConfig, StakingState, ADMIN, CLAIMS, DELEGATIONS, STAKING, STAKING_STATE,
+};
+use crate::vesting::assert_vested;
+
+// In staking mode the wrapped token is the liquid staking derivative. Deposits
+// of the bond denom are delegated and mint wrapped tokens at the exchange rate,
+// withdrawals burn them and unbond. The rate is the pool, bonded coins plus the
+// rewards booked but not yet delegated again, over the tokens minted this way,
+// so tokens minted otherwise, airdropped or vested do not dilute it.
+
+fn load_config(storage: &dyn Storage) -> Result<StakingConfig, ContractError> {
+    STAKING
+        .may_load(storage)?
+        .ok_or(ContractError::StakingDisabled {})
+}
+
+/// Whether deposits and withdrawals of the denom go through the staking pool
+pub fn is_bond_denom(storage: &dyn Storage, denom: &str) -> StdResult<bool> {
+    Ok(STAKING
+        .may_load(storage)?
+        .map(|config| config.bond_denom == denom)
+        .unwrap_or(false))
+}
+
+/// Value of the pool in the bond denom per staked wrapped token
+pub fn exchange_rate(state: &StakingState) -> Decimal {
+    if state.supply.is_zero() || state.total().is_zero() {
+        return Decimal::one();
+    }
+    Decimal::from_ratio(state.total(), state.supply)
+}
+
+/// Wrapped tokens minted for a bond of amount. The first bond sets the rate to
+/// one, and nothing more can be bonded once the pool lost all of its value.
+fn tokens_for(state: &StakingState, amount: Uint128) -> Uint128 {
+    if state.supply.is_zero() {
+        return amount;
+    }
+    if state.total().is_zero() {
+        return Uint128::zero();
+    }
+    amount.multiply_ratio(state.supply, state.total())
+}
+
+/// Amount of the bond denom the staked wrapped tokens are worth
+fn value_of(state: &StakingState, tokens: Uint128) -> Uint128 {
+    if state.supply.is_zero() {
+        return Uint128::zero();
+    }
+    tokens.multiply_ratio(state.total(), state.supply)
+}
+
+/// Wrapped tokens burned to unbond amount, rounded up so the pool never pays
+/// out more than they are worth
+fn tokens_to_burn(state: &StakingState, amount: Uint128) -> Uint128 {
+    let tokens = amount.multiply_ratio(state.supply, state.total());
+    if value_of(state, tokens) < amount {
+        tokens + Uint128::new(1)
+    } else {
+        tokens
+    }
+}
+
+/// Target share of amount for each validator by weight, the rounding
+/// remainder going to the heaviest one. All zero if no validator has weight.
+fn targets(amount: Uint128, validators: &[ValidatorWeight]) -> Vec<(String, Uint128)> {
+    let total: u64 = validators.iter().map(|v| v.weight).sum();
+    if total == 0 {
+        return validators
+            .iter()
+            .map(|v| (v.validator.clone(), Uint128::zero()))
+            .collect();
+    }
+    let mut shares: Vec<(String, Uint128)> = validators
+        .iter()
+        .map(|v| (v.validator.clone(), amount.multiply_ratio(v.weight, total)))
+        .collect();
+    let split: Uint128 = shares.iter().map(|(_, share)| *share).sum();
+    let heaviest = validators
+        .iter()
+        .max_by_key(|v| v.weight)
+        .map(|v| &v.validator);
+    if let Some((_, share)) = shares.iter_mut().find(|(v, _)| Some(v) == heaviest) {
+        *share += amount - split;
+    }
+    shares
+}
+
+/// Splits amount over the validators by weight, leaving out empty shares
+fn split_by_weight(
+    amount: Uint128,
+    validators: &[ValidatorWeight],
+) -> Result<Vec<(String, Uint128)>, ContractError> {
+    if validators.iter().all(|v| v.weight == 0) {
+        return Err(ContractError::NoValidators {});
+    }
+    let mut shares = targets(amount, validators);
+    shares.retain(|(_, share)| !share.is_zero());
+    Ok(shares)
+}
+
+/// Splits amount in proportion to the delegations, which are sorted largest
+/// first, the remainder going to the largest one
+fn split_pro_rata(amount: Uint128, delegations: &[(String, Uint128)]) -> Vec<(String, Uint128)> {
+    let total: Uint128 = delegations.iter().map(|(_, delegated)| *delegated).sum();
+    let mut shares: Vec<(String, Uint128)> = delegations
+        .iter()
+        .map(|(validator, delegated)| (validator.clone(), amount.multiply_ratio(*delegated, total)))
+        .collect();
+    let split: Uint128 = shares.iter().map(|(_, share)| *share).sum();
+    if let Some((_, share)) = shares.first_mut() {
+        *share += amount - split;
+    }
+    shares.retain(|(_, share)| !share.is_zero());
+    shares
+}
+
+/// Collects the staking messages of one execution. The staking module pays out
+/// the pending rewards of a validator on every delegate, undelegate and
+/// redelegate, so they are booked the first time a validator is touched. Later
+/// touches in the same execution see the same pending rewards, as the messages
+/// only run afterwards, and are not booked again.
+pub struct StakingOps<'a> {
+    querier: QuerierWrapper<'a>,
+    contract: Addr,
+    denom: String,
+    pub state: StakingState,
+    booked: Vec<String>,
+    pub messages: Vec<CosmosMsg>,
+}
+
+impl<'a> StakingOps<'a> {
+    pub fn new(
+        storage: &dyn Storage,
+        querier: QuerierWrapper<'a>,
+        env: &Env,
+        config: &StakingConfig,
+    ) -> StdResult<Self> {
+        Ok(StakingOps {
+            querier,
+            contract: env.contract.address.clone(),
+            denom: config.bond_denom.clone(),
+            state: STAKING_STATE.may_load(storage)?.unwrap_or_default(),
+            booked: vec![],
+            messages: vec![],
+        })
+    }
+
+    pub fn coin(&self, amount: Uint128) -> Coin {
+        Coin {
+            denom: self.denom.clone(),
+            amount,
+        }
+    }
+
+    /// Books the pending rewards of the validator, once per execution
+    pub fn book_rewards(&mut self, validator: &str) -> StdResult<Uint128> {
+        if self.booked.iter().any(|v| v == validator) {
+            return Ok(Uint128::zero());
+        }
+        self.booked.push(validator.to_string());
+        let rewards: Uint128 = self
+            .querier
+            .query_delegation(&self.contract, validator)?
+            .map(|delegation| {
+                delegation
+                    .accumulated_rewards
+                    .iter()
+                    .filter(|coin| coin.denom == self.denom)
+                    .map(|coin| coin.amount)
+                    .sum()
+            })
+            .unwrap_or_default();
+        self.state.rewards += rewards;
+        Ok(rewards)
+    }
+
+    pub fn delegate(
+        &mut self,
+        storage: &mut dyn Storage,
+        validator: &str,
+        amount: Uint128,
+    ) -> StdResult<()> {
+        self.book_rewards(validator)?;
+        DELEGATIONS.update(storage, validator, |d| -> StdResult<_> {
+            Ok(d.unwrap_or_default() + amount)
+        })?;
+        self.state.bonded += amount;
+        self.messages.push(
+            StakingMsg::Delegate {
+                validator: validator.to_string(),
+                amount: self.coin(amount),
+            }
+            .into(),
+        );
+        Ok(())
+    }
+
+    pub fn undelegate(
+        &mut self,
+        storage: &mut dyn Storage,
+        validator: &str,
+        amount: Uint128,
+    ) -> StdResult<()> {
+        self.book_rewards(validator)?;
+        DELEGATIONS.update(storage, validator, |d| -> StdResult<_> {
+            Ok(d.unwrap_or_default().checked_sub(amount)?)
+        })?;
+        self.state.bonded = self.state.bonded.checked_sub(amount)?;
+        self.messages.push(
+            StakingMsg::Undelegate {
+                validator: validator.to_string(),
+                amount: self.coin(amount),
+            }
+            .into(),
+        );
+        Ok(())
+    }
+
+    pub fn redelegate(
+        &mut self,
+        storage: &mut dyn Storage,
+        src_validator: &str,
+        dst_validator: &str,
+        amount: Uint128,
+    ) -> StdResult<()> {
+        self.book_rewards(src_validator)?;
+        self.book_rewards(dst_validator)?;
+        DELEGATIONS.update(storage, src_validator, |d| -> StdResult<_> {
+            Ok(d.unwrap_or_default().checked_sub(amount)?)
+        })?;
+        DELEGATIONS.update(storage, dst_validator, |d| -> StdResult<_> {
+            Ok(d.unwrap_or_default() + amount)
+        })?;
+        self.messages.push(
+            StakingMsg::Redelegate {
+                src_validator: src_validator.to_string(),
+                dst_validator: dst_validator.to_string(),
+                amount: self.coin(amount),
+            }
+            .into(),
+        );
+        Ok(())
+    }
+
+    /// How much of the delegation to the validator the staking module lets move
+    /// right away. Stake that arrived by a redelegation cannot move again until
+    /// that redelegation completes.
+    pub fn can_redelegate(&self, validator: &str) -> StdResult<Uint128> {
+        Ok(self
+            .querier
+            .query_delegation(&self.contract, validator)?
+            .filter(|delegation| delegation.can_redelegate.denom == self.denom)
+            .map(|delegation| delegation.can_redelegate.amount)
+            .unwrap_or_default())
+    }
+
+    pub fn withdraw_rewards(&mut self, validator: &str) -> StdResult<Uint128> {
+        let rewards = self.book_rewards(validator)?;
+        self.messages.push(
+            DistributionMsg::WithdrawDelegatorReward {
+                validator: validator.to_string(),
+            }
+            .into(),
+        );
+        Ok(rewards)
+    }
+
+    pub fn save(self, storage: &mut dyn Storage) -> StdResult<Vec<CosmosMsg>> {
+        STAKING_STATE.save(storage, &self.state)?;
+        Ok(self.messages)
+    }
+}
+
+/// Current delegations of the pool, largest first
+pub fn delegations(storage: &dyn Storage) -> StdResult<Vec<(String, Uint128)>> {
+    let mut delegations = DELEGATIONS
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (validator, amount) = item?;
+            Ok((String::from_utf8(validator)?, amount))
+        })
+        .filter(|item: &StdResult<(String, Uint128)>| {
+            item.as_ref()
+                .map(|(_, amount)| !amount.is_zero())
+                .unwrap_or(true)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    delegations.sort_by_key(|(_, amount)| std::cmp::Reverse(*amount));
+    Ok(delegations)
+}
+
+pub fn execute_update_staking(
+    deps: DepsMut,
+    info: MessageInfo,
+    bond_denom: String,
+    unbonding_period: Duration,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let config = match STAKING.may_load(deps.storage)? {
+        Some(config) => {
+            let state = STAKING_STATE.may_load(deps.storage)?.unwrap_or_default();
+            let staked = !state.total().is_zero() || !state.supply.is_zero();
+            if config.bond_denom != bond_denom && staked {
+                return Err(ContractError::BondDenomInUse {});
+            }
+            StakingConfig {
+                bond_denom: bond_denom.clone(),
+                unbonding_period,
+                ..config
+            }
+        }
+        None => StakingConfig {
+            bond_denom: bond_denom.clone(),
+            unbonding_period,
+            validators: vec![],
+            keeper_tip: Decimal::zero(),
+            protocol_fee: Decimal::zero(),
+            fee_recipient: None,
+        },
+    };
+    STAKING.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_staking")
+        .add_attribute("bond_denom", bond_denom))
+}
+
+pub fn execute_add_validator(
+    deps: DepsMut,
+    info: MessageInfo,
+    validator: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let mut config = load_config(deps.storage)?;
+    if config.validators.iter().any(|v| v.validator == validator) {
+        return Err(ContractError::ValidatorExists { validator });
+    }
+    if deps.querier.query_validator(&validator)?.is_none() {
+        return Err(ContractError::UnknownValidator { validator });
+    }
+    config.validators.push(ValidatorWeight {
+        validator: validator.clone(),
+        weight: 1,
+    });
+    STAKING.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "add_validator")
+        .add_attribute("validator", validator))
+}
+
+/// Takes the validator out of the set, moving its delegation to the others
+pub fn execute_remove_validator(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    validator: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let mut config = load_config(deps.storage)?;
+    if !config.validators.iter().any(|v| v.validator == validator) {
+        return Err(ContractError::UnknownValidator { validator });
+    }
+    config.validators.retain(|v| v.validator != validator);
+
+    let mut ops = StakingOps::new(deps.storage, deps.querier, &env, &config)?;
+    let delegated = DELEGATIONS
+        .may_load(deps.storage, &validator)?
+        .unwrap_or_default();
+    if !delegated.is_zero() {
+        for (dst, amount) in split_by_weight(delegated, &config.validators)? {
+            ops.redelegate(deps.storage, &validator, &dst, amount)?;
+        }
+    }
+    DELEGATIONS.remove(deps.storage, &validator);
+    STAKING.save(deps.storage, &config)?;
+    let messages = ops.save(deps.storage)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "remove_validator")
+        .add_attribute("validator", validator)
+        .add_attribute("redelegated", delegated))
+}
+
+/// Delegates a bond denom deposit and mints wrapped tokens at the exchange rate
+pub fn bond(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let config = load_config(deps.storage)?;
+    let stakes = split_by_weight(amount, &config.validators)?;
+    let mut ops = StakingOps::new(deps.storage, deps.querier, &env, &config)?;
+    let tokens = tokens_for(&ops.state, amount);
+    if tokens.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+
+    let before = TokenBalances::load(deps.storage, &[&info.sender])?;
+    mint_tokens(deps.storage, env.block.height, &info.sender, tokens)?;
+    ops.state.supply += tokens;
+    for (validator, amount) in stakes {
+        ops.delegate(deps.storage, &validator, amount)?;
+    }
+    let messages = ops.save(deps.storage)?;
+
+    let res = Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "bond")
+        .add_attribute("to", &info.sender)
+        .add_attribute("amount", amount)
+        .add_attribute("minted", tokens);
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+/// Burns the wrapped tokens of the sender worth amount of the bond denom and
+/// queues amount for the receiver, claimable once the unbonding period is over.
+/// Booked rewards are used first, the rest is undelegated from the largest
+/// delegations.
+pub fn unbond(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    receiver: Addr,
+) -> Result<Response, ContractError> {
+    let config = load_config(deps.storage)?;
+    let mut ops = StakingOps::new(deps.storage, deps.querier, &env, &config)?;
+    if amount > ops.state.total() {
+        return Err(ContractError::InsufficientStake {
+            available: ops.state.total(),
+        });
+    }
+    let tokens = tokens_to_burn(&ops.state, amount);
+    assert_vested(deps.storage, &env.block, &info.sender, tokens)?;
+
+    let before = TokenBalances::load(deps.storage, &[&info.sender])?;
+    decrease_token_balance(deps.storage, env.block.height, &info.sender, tokens)?;
+    decrease_total_supply(deps.storage, env.block.height, tokens)?;
+    ops.state.supply = ops
+        .state
+        .supply
+        .checked_sub(tokens)
+        .map_err(StdError::from)?;
+
+    let from_rewards = std::cmp::min(amount, ops.state.rewards);
+    ops.state.rewards -= from_rewards;
+    let mut remaining = amount - from_rewards;
+    for (validator, delegated) in delegations(deps.storage)? {
+        if remaining.is_zero() {
+            break;
+        }
+        let undelegated = std::cmp::min(remaining, delegated);
+        ops.undelegate(deps.storage, &validator, undelegated)?;
+        remaining -= undelegated;
+    }
+    let messages = ops.save(deps.storage)?;
+
+    CLAIMS.create_claim(
+        deps.storage,
+        &receiver,
+        amount,
+        config.unbonding_period.after(&env.block),
+    )?;
+
+    let res = Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "unbond")
+        .add_attribute("from", &info.sender)
+        .add_attribute("to", receiver)
+        .add_attribute("amount", amount)
+        .add_attribute("burned", tokens);
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+/// Pays out the claims of the sender whose unbonding period is over
+pub fn execute_claim(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = load_config(deps.storage)?;
+    assert_sender_not_frozen(deps.storage, &info.sender)?;
+    assert_no_flash_loan(deps.storage)?;
+    let amount = CLAIMS.claim_tokens(deps.storage, &info.sender, &env.block, None)?;
+    if amount.is_zero() {
+        return Err(ContractError::NothingToClaim {});
+    }
+
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![Coin {
+                denom: config.bond_denom,
+                amount,
+            }],
+        })
+        .add_attribute("action", "claim")
+        .add_attribute("to", info.sender)
+        .add_attribute("amount", amount))
+}
+
+/// Withdraws the rewards of every delegation into the contract, which raises
+/// the exchange rate. Anyone may call it.
+pub fn execute_collect_rewards(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = load_config(deps.storage)?;
+    assert_no_flash_loan(deps.storage)?;
+    let mut ops = StakingOps::new(deps.storage, deps.querier, &env, &config)?;
+    let mut collected = Uint128::zero();
+    for (validator, _) in delegations(deps.storage)? {
+        collected += ops.withdraw_rewards(&validator)?;
+    }
+    let messages = ops.save(deps.storage)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "collect_rewards")
+        .add_attribute("amount", collected))
+}
+
+pub fn execute_set_validator_weights(
+    deps: DepsMut,
+    info: MessageInfo,
+    weights: Vec<ValidatorWeight>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let mut config = load_config(deps.storage)?;
+    for update in weights {
+        let entry = config
+            .validators
+            .iter_mut()
+            .find(|v| v.validator == update.validator)
+            .ok_or(ContractError::UnknownValidator {
+                validator: update.validator,
+            })?;
+        entry.weight = update.weight;
+    }
+    if config.validators.iter().all(|v| v.weight == 0) {
+        return Err(ContractError::NoValidators {});
+    }
+    STAKING.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attribute("action", "set_validator_weights"))
+}
+
+/// Redelegates from the validators above their target to the ones below it.
+/// Only what the staking module lets move right away is moved, so it may take
+/// a few calls spaced by the unbonding time to reach the targets.
+pub fn execute_rebalance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let config = load_config(deps.storage)?;
+    let current = delegations(deps.storage)?;
+    let bonded: Uint128 = current.iter().map(|(_, amount)| *amount).sum();
+    let targets = targets(bonded, &config.validators);
+    let target_of = |validator: &str| {
+        targets
+            .iter()
+            .find(|(v, _)| v == validator)
+            .map(|(_, target)| *target)
+            .unwrap_or_default()
+    };
+    let current_of = |validator: &str| {
+        current
+            .iter()
+            .find(|(v, _)| v == validator)
+            .map(|(_, amount)| *amount)
+            .unwrap_or_default()
+    };
+
+    let mut surpluses: Vec<(String, Uint128)> = current
+        .iter()
+        .map(|(v, amount)| (v.clone(), amount.saturating_sub(target_of(v))))
+        .filter(|(_, surplus)| !surplus.is_zero())
+        .collect();
+    surpluses.sort_by_key(|(_, surplus)| std::cmp::Reverse(*surplus));
+    let mut deficits: Vec<(String, Uint128)> = targets
+        .iter()
+        .map(|(v, target)| (v.clone(), target.saturating_sub(current_of(v))))
+        .filter(|(_, deficit)| !deficit.is_zero())
+        .collect();
+    deficits.sort_by_key(|(_, deficit)| std::cmp::Reverse(*deficit));
+
+    let mut ops = StakingOps::new(deps.storage, deps.querier, &env, &config)?;
+    let mut moved = Uint128::zero();
+    for (src, surplus) in surpluses {
+        let mut left = std::cmp::min(surplus, ops.can_redelegate(&src)?);
+        for (dst, deficit) in deficits.iter_mut() {
+            if left.is_zero() {
+                break;
+            }
+            let amount = std::cmp::min(left, *deficit);
+            if amount.is_zero() {
+                continue;
+            }
+            ops.redelegate(deps.storage, &src, dst, amount)?;
+            *deficit -= amount;
+            left -= amount;
+            moved += amount;
+        }
+    }
+    let messages = ops.save(deps.storage)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "rebalance")
+        .add_attribute("moved", moved))
+}
+
+pub fn execute_update_compound_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    keeper_tip: Decimal,
+    protocol_fee: Decimal,
+    fee_recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let mut config = load_config(deps.storage)?;
+    if keeper_tip + protocol_fee > Decimal::one() {
+        return Err(ContractError::InvalidFee {});
+    }
+    let fee_recipient = fee_recipient
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    if fee_recipient.is_none() && !protocol_fee.is_zero() {
+        return Err(ContractError::NoFeeRecipient {});
+    }
+    config.keeper_tip = keeper_tip;
+    config.protocol_fee = protocol_fee;
+    config.fee_recipient = fee_recipient;
+    STAKING.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_compound_fees")
+        .add_attribute("keeper_tip", keeper_tip.to_string())
+        .add_attribute("protocol_fee", protocol_fee.to_string()))
+}
+
+/// Withdraws the rewards of every delegation and delegates them again, minus
+/// the keeper tip for the sender and the protocol fee. Rewards booked earlier
+/// are compounded as well.
+pub fn execute_compound(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = load_config(deps.storage)?;
+    assert_no_flash_loan(deps.storage)?;
+    let mut ops = StakingOps::new(deps.storage, deps.querier, &env, &config)?;
+    let delegations = delegations(deps.storage)?;
+    for (validator, _) in &delegations {
+        ops.withdraw_rewards(validator)?;
+    }
+
+    let rewards = ops.state.rewards;
+    let keeper_tip = rewards * config.keeper_tip;
+    let protocol_fee = rewards * config.protocol_fee;
+    let restaked = rewards - keeper_tip - protocol_fee;
+    ops.state.rewards = Uint128::zero();
+    if !keeper_tip.is_zero() {
+        ops.messages.push(
+            BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![ops.coin(keeper_tip)],
+            }
+            .into(),
+        );
+    }
+    if let (Some(recipient), false) = (&config.fee_recipient, protocol_fee.is_zero()) {
+        ops.messages.push(
+            BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![ops.coin(protocol_fee)],
+            }
+            .into(),
+        );
+    }
+
+    let shares = if delegations.is_empty() {
+        split_by_weight(restaked, &config.validators)?
+    } else {
+        split_pro_rata(restaked, &delegations)
+    };
+    for (validator, amount) in shares {
+        ops.delegate(deps.storage, &validator, amount)?;
+    }
+    let messages = ops.save(deps.storage)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "compound")
+        .add_attribute("rewards", rewards)
+        .add_attribute("keeper_tip", keeper_tip)
+        .add_attribute("protocol_fee", protocol_fee)
+        .add_attribute("restaked", restaked))
+}
+
+pub fn query_staking(deps: Deps) -> StdResult<StakingResponse> {
+    let config = STAKING.load(deps.storage)?;
+    let state = STAKING_STATE.may_load(deps.storage)?.unwrap_or_default();
+    Ok(StakingResponse {
+        exchange_rate: exchange_rate(&state),
+        bond_denom: config.bond_denom,
+        unbonding_period: config.unbonding_period,
+        validators: config.validators,
+        bonded: state.bonded,
+        rewards: state.rewards,
+        supply: state.supply,
+        keeper_tip: config.keeper_tip,
+        protocol_fee: config.protocol_fee,
+        fee_recipient: config.fee_recipient.map(String::from),
+    })
+}
+
+/// Current and target stake of each validator in the set, then of the
+/// validators still holding stake outside of it
+pub fn query_delegations(deps: Deps) -> StdResult<DelegationsResponse> {
+    let config = STAKING.load(deps.storage)?;
+    let current = delegations(deps.storage)?;
+    let bonded: Uint128 = current.iter().map(|(_, amount)| *amount).sum();
+    let current_of = |validator: &str| {
+        current
+            .iter()
+            .find(|(v, _)| v == validator)
+            .map(|(_, amount)| *amount)
+            .unwrap_or_default()
+    };
+
+    let mut delegations: Vec<DelegationInfo> = config
+        .validators
+        .iter()
+        .zip(targets(bonded, &config.validators))
+        .map(|(v, (_, target))| DelegationInfo {
+            validator: v.validator.clone(),
+            weight: v.weight,
+            current: current_of(&v.validator),
+            target,
+        })
+        .collect();
+    for (validator, amount) in &current {
+        if !config.validators.iter().any(|v| &v.validator == validator) {
+            delegations.push(DelegationInfo {
+                validator: validator.clone(),
+                weight: 0,
+                current: *amount,
+                target: Uint128::zero(),
+            });
+        }
+    }
+    Ok(DelegationsResponse { delegations })
+}
+
+pub fn query_claims(deps: Deps, address: String) -> StdResult<ClaimsResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    CLAIMS.query_claims(deps, &address)
+}
@@ -1,6 +1,249 @@
-use cosmwasm_std::{Addr, Uint128};
-use cw_storage_plus::Map;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{Addr, Decimal, IbcEndpoint, Uint128};
+use cw0::{Duration, Expiration};
+use cw_controllers::{Admin, Claims, Hooks};
+use cw_storage_plus::{
+    Index, IndexList, IndexedMap, Item, Map, MultiIndex, SnapshotMap, Strategy, U64Key, U8Key,
+};
+
+use crate::erc20::ValidatorWeight;
+use crate::vesting::VestingSchedule;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct ComplianceConfig {
+    /// Account that may freeze, unfreeze and seize alongside the admin
+    pub officer: Option<Addr>,
+    /// Destination of seized funds. Seizure is disabled while unset
+    pub recovery_address: Option<Addr>,
+}
+
+/// An open flash loan, settled by FinishFlashLoan within the same transaction
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FlashLoanState {
+    pub denom: String,
+    /// Reserve before the loan plus the fee
+    pub expected_balance: Uint128,
+    /// Booked to FLASH_LOAN_FEES once the loan is repaid
+    pub fee: Uint128,
+}
+
+pub const ADMIN: Admin = Admin::new("admin");
+
+/// Contracts notified of every balance change
+pub const HOOKS: Hooks = Hooks::new("hooks");
 
 pub const WHITELISTED_COINS: Map<&str, bool> = Map::new("whitelisted_coins");
 
-pub const BALANCES: Map<(&Addr, &str), Uint128> = Map::new("balances");
+/// Deposited native balances per account and denom
+pub const BALANCES: SnapshotMap<(&Addr, &str), Uint128> = SnapshotMap::new(
+    "balances",
+    "balances__checkpoints",
+    "balances__changelog",
+    Strategy::EveryBlock,
+);
+
+/// Wrapped token balances. Shares its namespace with the cw20-base balances so
+/// existing entries stay readable.
+pub const TOKEN_BALANCES: SnapshotMap<&Addr, Uint128> = SnapshotMap::new(
+    "balance",
+    "balance__checkpoints",
+    "balance__changelog",
+    Strategy::EveryBlock,
+);
+
+/// Wrapped token supply, kept as the single TOTAL_SUPPLY_KEY entry as
+/// cw-storage-plus 0.8 has no SnapshotItem. The total_supply of the cw20-base
+/// TOKEN_INFO is kept in sync with it.
+pub const TOTAL_SUPPLY: SnapshotMap<&str, Uint128> = SnapshotMap::new(
+    "total_supply",
+    "total_supply__checkpoints",
+    "total_supply__changelog",
+    Strategy::EveryBlock,
+);
+
+pub const TOTAL_SUPPLY_KEY: &str = "supply";
+
+pub const COMPLIANCE: Item<ComplianceConfig> = Item::new("compliance");
+
+pub const FROZEN_ACCOUNTS: Map<&Addr, bool> = Map::new("frozen_accounts");
+
+/// Next permit nonce expected from each owner
+pub const PERMIT_NONCES: Map<&Addr, u64> = Map::new("permit_nonces");
+
+pub const FLASH_LOAN_FEE: Item<Decimal> = Item::new("flash_loan_fee");
+
+pub const FLASH_LOAN: Item<FlashLoanState> = Item::new("flash_loan");
+
+/// Flash loan fees collected and not withdrawn yet, by denom
+pub const FLASH_LOAN_FEES: Map<&str, Uint128> = Map::new("flash_loan_fees");
+
+pub const VESTING: Map<&Addr, VestingSchedule> = Map::new("vesting");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakingConfig {
+    /// Deposits of this denom are delegated instead of held
+    pub bond_denom: String,
+    /// Wait before unbonded coins can be claimed, at least the chain unbonding time
+    pub unbonding_period: Duration,
+    /// Validators deposits are delegated to, and their target share of the stake
+    pub validators: Vec<ValidatorWeight>,
+    /// Share of compounded rewards paid to whoever calls Compound
+    pub keeper_tip: Decimal,
+    /// Share of compounded rewards paid to the fee recipient
+    pub protocol_fee: Decimal,
+    pub fee_recipient: Option<Addr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct StakingState {
+    /// Coins currently delegated by the pool
+    pub bonded: Uint128,
+    /// Rewards paid out to the contract and not delegated again yet
+    pub rewards: Uint128,
+    /// Wrapped tokens minted by bonds and not burned by unbonds yet, each worth
+    /// total() / supply of the bond denom
+    #[serde(default)]
+    pub supply: Uint128,
+}
+
+impl StakingState {
+    pub fn total(&self) -> Uint128 {
+        self.bonded + self.rewards
+    }
+}
+
+/// Unset unless the liquid staking mode is on
+pub const STAKING: Item<StakingConfig> = Item::new("staking");
+
+pub const STAKING_STATE: Item<StakingState> = Item::new("staking_state");
+
+/// Amount delegated to each validator, as booked by the contract
+pub const DELEGATIONS: Map<&str, Uint128> = Map::new("delegations");
+
+/// Unbonded coins waiting for the unbonding period, by receiver
+pub const CLAIMS: Claims = Claims::new("claims");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ChannelInfo {
+    /// Id of the channel on this chain
+    pub id: String,
+    /// The remote channel and port
+    pub counterparty_endpoint: IbcEndpoint,
+    pub connection_id: String,
+}
+
+/// ICS-20 channels, by id
+pub const CHANNEL_INFO: Map<&str, ChannelInfo> = Map::new("channel_info");
+
+/// Tokens sent over each channel and not returned yet, the most that can come back
+pub const CHANNEL_BALANCES: Map<&str, Uint128> = Map::new("channel_balances");
+
+/// A native withdrawal whose bank send is in flight
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingWithdrawal {
+    pub owner: Addr,
+    pub recipient: Addr,
+    pub denom: String,
+    pub amount: Uint128,
+}
+
+/// The native withdrawal being sent, read back and cleared when its bank send replies
+pub const PENDING_WITHDRAWAL: Item<PendingWithdrawal> = Item::new("pending_withdrawal");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FailedWithdrawal {
+    pub id: u64,
+    pub recipient: Addr,
+    pub denom: String,
+    pub amount: Uint128,
+    /// Error returned by the bank module
+    pub error: String,
+}
+
+pub const FAILED_WITHDRAWAL_SEQ: Item<u64> = Item::new("failed_withdrawal_seq");
+
+/// Withdrawals whose bank send failed, by owner and id. The amount is back in the
+/// owner's balance until the withdrawal is retried.
+pub const FAILED_WITHDRAWALS: Map<(&Addr, U64Key), FailedWithdrawal> =
+    Map::new("failed_withdrawals");
+
+/// Wrapped tokens held by the contract until the arbiter approves or the escrow expires
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Escrow {
+    pub id: u64,
+    pub sender: Addr,
+    pub recipient: Addr,
+    pub arbiter: Addr,
+    pub amount: Uint128,
+    pub expiration: Expiration,
+}
+
+pub struct EscrowIndexes<'a> {
+    pub sender: MultiIndex<'a, (Vec<u8>, Vec<u8>), Escrow>,
+    pub recipient: MultiIndex<'a, (Vec<u8>, Vec<u8>), Escrow>,
+}
+
+impl<'a> IndexList<Escrow> for EscrowIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Escrow>> + '_> {
+        let v: Vec<&dyn Index<Escrow>> = vec![&self.sender, &self.recipient];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Open escrows by id, indexed by sender and by recipient
+pub fn escrows<'a>() -> IndexedMap<'a, U64Key, Escrow, EscrowIndexes<'a>> {
+    let indexes = EscrowIndexes {
+        sender: MultiIndex::new(
+            |e, pk| (e.sender.as_bytes().to_vec(), pk),
+            "escrows",
+            "escrows__sender",
+        ),
+        recipient: MultiIndex::new(
+            |e, pk| (e.recipient.as_bytes().to_vec(), pk),
+            "escrows",
+            "escrows__recipient",
+        ),
+    };
+    IndexedMap::new("escrows", indexes)
+}
+
+pub const ESCROW_SEQ: Item<u64> = Item::new("escrow_seq");
+
+/// Wrapped tokens held by the contract and paid to the recipient linearly between
+/// start_time and end_time, in seconds since the epoch
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Stream {
+    pub id: u64,
+    pub sender: Addr,
+    pub recipient: Addr,
+    pub amount: Uint128,
+    /// Paid out to the recipient so far
+    pub withdrawn: Uint128,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+/// Open streams, by id
+pub const STREAMS: Map<U64Key, Stream> = Map::new("streams");
+
+pub const STREAM_SEQ: Item<u64> = Item::new("stream_seq");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MerkleStage {
+    /// Hex encoded SHA-256 root of the tree
+    pub root: String,
+    pub expiration: Expiration,
+    /// Most that can be minted through claims of the stage
+    pub total_amount: Uint128,
+    pub claimed: Uint128,
+    /// Set once the admin has minted the unclaimed rest
+    pub reclaimed: bool,
+}
+
+/// Airdrop stages, by stage number
+pub const MERKLE_STAGES: Map<U8Key, MerkleStage> = Map::new("merkle_stages");
+
+/// Set once the address has claimed its airdrop of the stage
+pub const AIRDROP_CLAIMS: Map<(&Addr, U8Key), bool> = Map::new("airdrop_claims");
@@ -0,0 +1,209 @@
+use cosmwasm_std::{BlockInfo, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128};
+use cw_storage_plus::U64Key;
+
+use crate::compliance::{assert_recipient_not_frozen, assert_sender_not_frozen};
+use crate::erc20::StreamResponse;
+use crate::error::ContractError;
+use crate::hooks::{add_balance_hooks, TokenBalances};
+use crate::ledger::move_token_balance;
+use crate::state::{Stream, STREAMS, STREAM_SEQ};
+use crate::vesting::assert_vested;
+
+impl Stream {
+    /// Tokens earned by the recipient so far, withdrawn or not
+    pub fn streamed(&self, block: &BlockInfo) -> Uint128 {
+        let now = block.time.seconds();
+        if now <= self.start_time {
+            Uint128::zero()
+        } else if now >= self.end_time {
+            self.amount
+        } else {
+            self.amount
+                .multiply_ratio(now - self.start_time, self.end_time - self.start_time)
+        }
+    }
+
+    pub fn withdrawable(&self, block: &BlockInfo) -> Uint128 {
+        self.streamed(block) - self.withdrawn
+    }
+}
+
+/// Moves amount tokens of the sender into the contract, to be streamed to the
+/// recipient between start and end
+pub fn execute_create_stream(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: String,
+    amount: Uint128,
+    start_time: u64,
+    end_time: u64,
+) -> Result<Response, ContractError> {
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    if start_time >= end_time || end_time <= env.block.time.seconds() {
+        return Err(ContractError::InvalidStream {});
+    }
+    let recipient = deps.api.addr_validate(&recipient)?;
+    if recipient == info.sender {
+        return Err(ContractError::CannotSetOwnAccount {});
+    }
+    assert_sender_not_frozen(deps.storage, &info.sender)?;
+    assert_recipient_not_frozen(deps.storage, &recipient)?;
+    assert_vested(deps.storage, &env.block, &info.sender, amount)?;
+
+    let contract = env.contract.address;
+    let before = TokenBalances::load(deps.storage, &[&info.sender, &contract])?;
+    move_token_balance(
+        deps.storage,
+        env.block.height,
+        &info.sender,
+        &contract,
+        amount,
+    )?;
+
+    let id = STREAM_SEQ.may_load(deps.storage)?.unwrap_or_default() + 1;
+    STREAM_SEQ.save(deps.storage, &id)?;
+    let stream = Stream {
+        id,
+        sender: info.sender,
+        recipient,
+        amount,
+        withdrawn: Uint128::zero(),
+        start_time,
+        end_time,
+    };
+    STREAMS.save(deps.storage, U64Key::new(id), &stream)?;
+
+    let res = Response::new()
+        .add_attribute("action", "create_stream")
+        .add_attribute("id", id.to_string())
+        .add_attribute("from", stream.sender)
+        .add_attribute("to", stream.recipient)
+        .add_attribute("amount", amount);
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+/// Recipient only. Pays out everything streamed and not withdrawn yet
+pub fn execute_withdraw_from_stream(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let mut stream = load_stream(deps.as_ref(), id)?;
+    if info.sender != stream.recipient {
+        return Err(ContractError::Unauthorized {});
+    }
+    assert_recipient_not_frozen(deps.storage, &stream.recipient)?;
+    let amount = stream.withdrawable(&env.block);
+    if amount.is_zero() {
+        return Err(ContractError::NothingToClaim {});
+    }
+
+    stream.withdrawn += amount;
+    // a fully paid stream is closed
+    if stream.withdrawn == stream.amount {
+        STREAMS.remove(deps.storage, U64Key::new(id));
+    } else {
+        STREAMS.save(deps.storage, U64Key::new(id), &stream)?;
+    }
+    let contract = env.contract.address;
+    let before = TokenBalances::load(deps.storage, &[&contract, &stream.recipient])?;
+    move_token_balance(
+        deps.storage,
+        env.block.height,
+        &contract,
+        &stream.recipient,
+        amount,
+    )?;
+
+    let res = Response::new()
+        .add_attribute("action", "withdraw_from_stream")
+        .add_attribute("id", id.to_string())
+        .add_attribute("to", stream.recipient)
+        .add_attribute("amount", amount);
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+/// Sender only. Closes the stream, paying the recipient what was streamed so far
+/// and returning the rest to the sender
+pub fn execute_cancel_stream(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let stream = load_stream(deps.as_ref(), id)?;
+    if info.sender != stream.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let earned = stream.withdrawable(&env.block);
+    let refund = stream.amount - stream.streamed(&env.block);
+    if !earned.is_zero() {
+        assert_recipient_not_frozen(deps.storage, &stream.recipient)?;
+    }
+    if !refund.is_zero() {
+        assert_recipient_not_frozen(deps.storage, &stream.sender)?;
+    }
+    STREAMS.remove(deps.storage, U64Key::new(id));
+
+    let contract = env.contract.address;
+    let before = TokenBalances::load(
+        deps.storage,
+        &[&contract, &stream.recipient, &stream.sender],
+    )?;
+    move_token_balance(
+        deps.storage,
+        env.block.height,
+        &contract,
+        &stream.recipient,
+        earned,
+    )?;
+    move_token_balance(
+        deps.storage,
+        env.block.height,
+        &contract,
+        &stream.sender,
+        refund,
+    )?;
+
+    let res = Response::new()
+        .add_attribute("action", "cancel_stream")
+        .add_attribute("id", id.to_string())
+        .add_attribute("paid", earned)
+        .add_attribute("refunded", refund);
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+fn load_stream(deps: Deps, id: u64) -> Result<Stream, ContractError> {
+    STREAMS
+        .may_load(deps.storage, U64Key::new(id))?
+        .ok_or(ContractError::UnknownStream { id })
+}
+
+pub fn query_stream(deps: Deps, env: Env, id: u64) -> StdResult<StreamResponse> {
+    let stream = STREAMS.may_load(deps.storage, U64Key::new(id))?;
+    let (streamed, withdrawable) = match &stream {
+        Some(stream) => (stream.streamed(&env.block), stream.withdrawable(&env.block)),
+        None => (Uint128::zero(), Uint128::zero()),
+    };
+    Ok(StreamResponse {
+        stream,
+        streamed,
+        withdrawable,
+    })
+}
@@ -1,15 +1,46 @@
-use crate::contract::{execute, instantiate};
+use crate::contract::{
+    execute, instantiate, migrate, query, query_balance, query_token_info, reply,
+};
+use crate::erc20::{
+    AirdropClaimedResponse, ChannelResponse, DelegationInfo, DelegationsResponse, EscrowResponse,
+    EscrowsResponse, ExecuteMsg, FailedWithdrawal, FailedWithdrawalsResponse, FlashLoanFeeResponse,
+    FrozenAccountsResponse, HooksResponse, InstantiateMsg, ListChannelsResponse,
+    MerkleStageResponse, MigrateMsg, PermitNonceResponse, QueryMsg, StakingResponse,
+    StreamResponse, TotalSupplyResponse, ValidatorWeight, VestingAccount, VestingResponse,
+};
+use crate::hooks::{BalanceChangedHookMsg, BalanceDiff};
+use crate::ibc::{
+    ibc_channel_connect, ibc_channel_open, ibc_packet_ack, ibc_packet_receive, ibc_packet_timeout,
+    Ics20Ack, Ics20Packet, DEFAULT_IBC_TIMEOUT, ICS20_VERSION,
+};
+use crate::permit::{pubkey_to_address, PermitMsg};
+use crate::vesting::VestingSchedule;
+use crate::withdrawals::WITHDRAW_REPLY_ID;
+use crate::ContractError;
 use cosmwasm_std::{
-    testing::{mock_dependencies, mock_env, mock_info},
-    to_binary, Binary, Coin, Deps, DepsMut, StdError, Uint128,
+    attr, from_binary,
+    testing::{
+        mock_dependencies, mock_env, mock_ibc_channel_connect_ack, mock_ibc_channel_open_init,
+        mock_ibc_channel_open_try, mock_ibc_packet_ack, mock_ibc_packet_recv,
+        mock_ibc_packet_timeout, mock_info, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR,
+    },
+    to_binary, Addr, Api, BankMsg, Binary, CanonicalAddr, Coin, ContractResult, Decimal, Deps,
+    DepsMut, DistributionMsg, Env, FullDelegation, IbcAcknowledgement, IbcMsg, IbcOrder,
+    IbcPacketAckMsg, IbcTimeout, OwnedDeps, RecoverPubkeyError, Reply, Response, StakingMsg,
+    StdError, StdResult, SubMsg, SubMsgExecutionResponse, Timestamp, Uint128, Validator,
+    VerificationError, WasmMsg,
 };
-use cw2::{get_contract_version, ContractVersion};
-use cw20::{BalanceResponse, Cw20Coin, MinterResponse, TokenInfoResponse};
-use cw20_base::{
-    contract::{query_balance, query_minter, query_token_info},
-    ContractError,
+use cw0::Duration;
+use cw2::{get_contract_version, set_contract_version, ContractVersion};
+use cw20::{
+    AllowanceResponse, BalanceResponse, Cw20Coin, Expiration, MinterResponse, TokenInfoResponse,
 };
-use crate::erc20::{ExecuteMsg, InstantiateMsg};
+use cw20_base::contract::{instantiate as cw_instantiate, query_minter};
+use cw20_base::msg::InstantiateMsg as BaseInstantiateMsg;
+use cw20_base::state::TOKEN_INFO;
+use cw_controllers::{AdminResponse, Claim, ClaimsResponse};
+use k256::ecdsa::{signature::Signer, Signature, SigningKey};
+use sha2::{Digest, Sha256};
 
 const INIT_ADDRESS: &str = "contract_initiator";
 const RECIPIENT: &str = "recipient";
@@ -60,6 +91,7 @@ fn _do_instantiate(
         }],
         mint: mint.clone(),
         marketing: None,
+        vesting: vec![],
     };
     let info = mock_info(INIT_ADDRESS, &[]);
     let env = mock_env();
@@ -80,7 +112,7 @@ fn _do_instantiate(
     assert_eq!(query_minter(deps.as_ref()).unwrap(), mint);
     let version_info: ContractVersion = ContractVersion {
         contract: String::from("erc-20"),
-        version: String::from("1.0.0"),
+        version: String::from("1.1.0"),
     };
     assert_eq!(version_info, get_contract_version(deps.storage).unwrap());
 
@@ -104,6 +136,7 @@ fn test_basic() {
         }],
         mint: None,
         marketing: None,
+        vesting: vec![],
     };
     let info = mock_info(INIT_ADDRESS, &[]);
     let env = mock_env();
@@ -125,6 +158,7 @@ fn test_basic() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_transfer() {
     let mut deps = mock_dependencies(&[Coin {
         amount: Uint128::default(),
@@ -159,6 +193,7 @@ fn test_transfer() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_send() {
     let mut deps = mock_dependencies(&[Coin {
         amount: Uint128::default(),
@@ -248,6 +283,7 @@ fn mintable_over_cap() {
             cap: Some(limit),
         }),
         marketing: None,
+        vesting: vec![],
     };
     let info = mock_info("creator", &[]);
     let env = mock_env();
@@ -376,6 +412,7 @@ fn instantiate_multiple_accounts() {
         ],
         mint: None,
         marketing: None,
+        vesting: vec![],
     };
     let info = mock_info("creator", &[]);
     let env = mock_env();
@@ -418,3 +455,2713 @@ fn queries_work() {
 
     assert_eq!(loaded.balance, Uint128::zero());
 }
+
+fn get_denom_balance<T: Into<String>>(deps: Deps, address: T, denom: &str) -> Uint128 {
+    let msg = QueryMsg::BalanceDenom {
+        address: address.into(),
+        denom: denom.to_string(),
+    };
+    let res: BalanceResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+    res.balance
+}
+
+// whitelists the denom and deposits amount of it for the depositor
+fn do_deposit(deps: DepsMut, depositor: &str, denom: &str, amount: u128) {
+    let mut deps = deps;
+    let msg = ExecuteMsg::WhiteListCoin {
+        denom: denom.to_string(),
+        status: true,
+    };
+    execute(deps.branch(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+    let info = mock_info(depositor, &[Coin::new(amount, denom)]);
+    execute(deps, mock_env(), info, ExecuteMsg::Deposit {}).unwrap();
+}
+
+#[test]
+fn withdraw_debits_sender() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(100));
+    do_deposit(deps.as_mut(), RECIPIENT, "uluna", 500);
+
+    let msg = ExecuteMsg::Withdraw {
+        denom: "uluna".to_string(),
+        amount: Uint128::new(200),
+        recipient: Some("lucky".to_string()),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(RECIPIENT, &[]), msg).unwrap();
+    assert_eq!(1, res.messages.len());
+    assert_eq!(
+        get_denom_balance(deps.as_ref(), RECIPIENT, "uluna"),
+        Uint128::new(300)
+    );
+    assert_eq!(
+        get_denom_balance(deps.as_ref(), "lucky", "uluna"),
+        Uint128::zero()
+    );
+}
+
+#[test]
+fn batch_transfer_is_all_or_nothing() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(1000));
+    let info = mock_info(RECIPIENT, &[]);
+
+    let msg = ExecuteMsg::BatchTransfer { transfers: vec![] };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(err, ContractError::EmptyBatch {});
+
+    // one bad entry fails the whole batch
+    let msg = ExecuteMsg::BatchTransfer {
+        transfers: vec![
+            ("alice".to_string(), Uint128::new(100)),
+            ("bob".to_string(), Uint128::zero()),
+        ],
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidZeroAmount {});
+    let msg = ExecuteMsg::BatchTransfer {
+        transfers: vec![
+            ("alice".to_string(), Uint128::new(600)),
+            ("bob".to_string(), Uint128::new(600)),
+        ],
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::Std(StdError::Overflow { .. })));
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(1000));
+
+    let msg = ExecuteMsg::BatchTransfer {
+        transfers: vec![
+            ("alice".to_string(), Uint128::new(100)),
+            ("bob".to_string(), Uint128::new(250)),
+            ("alice".to_string(), Uint128::new(50)),
+        ],
+    };
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(res.attributes[2], attr("recipients", "3"));
+    assert_eq!(res.attributes[3], attr("amount", "400"));
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(600));
+    assert_eq!(get_balance(deps.as_ref(), "alice"), Uint128::new(150));
+    assert_eq!(get_balance(deps.as_ref(), "bob"), Uint128::new(250));
+}
+
+#[test]
+fn batch_withdraw_pays_every_recipient() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(100));
+    do_deposit(deps.as_mut(), RECIPIENT, "uluna", 500);
+    let info = mock_info(RECIPIENT, &[]);
+
+    let msg = ExecuteMsg::BatchWithdraw {
+        denom: "uluna".to_string(),
+        withdrawals: vec![
+            ("alice".to_string(), Uint128::new(300)),
+            ("bob".to_string(), Uint128::new(300)),
+        ],
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::Std(StdError::Overflow { .. })));
+    assert_eq!(
+        get_denom_balance(deps.as_ref(), RECIPIENT, "uluna"),
+        Uint128::new(500)
+    );
+
+    let msg = ExecuteMsg::BatchWithdraw {
+        denom: "uluna".to_string(),
+        withdrawals: vec![
+            ("alice".to_string(), Uint128::new(100)),
+            ("bob".to_string(), Uint128::new(150)),
+            ("alice".to_string(), Uint128::new(50)),
+        ],
+    };
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let send = |to: &str, amount: u128| {
+        SubMsg::new(BankMsg::Send {
+            to_address: to.to_string(),
+            amount: vec![Coin::new(amount, "uluna")],
+        })
+    };
+    assert_eq!(res.messages, vec![send("alice", 150), send("bob", 150)]);
+    assert_eq!(res.attributes[2], attr("recipients", "2"));
+    assert_eq!(
+        get_denom_balance(deps.as_ref(), RECIPIENT, "uluna"),
+        Uint128::new(200)
+    );
+}
+
+fn escrows_by(deps: Deps, sender: Option<&str>, recipient: Option<&str>) -> Vec<u64> {
+    let msg = match (sender, recipient) {
+        (Some(sender), _) => QueryMsg::EscrowsBySender {
+            sender: sender.to_string(),
+            start_after: None,
+            limit: None,
+        },
+        (_, recipient) => QueryMsg::EscrowsByRecipient {
+            recipient: recipient.unwrap().to_string(),
+            start_after: None,
+            limit: None,
+        },
+    };
+    let res: EscrowsResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+    res.escrows.into_iter().map(|e| e.id).collect()
+}
+
+fn create_escrow(recipient: &str, amount: u128) -> ExecuteMsg {
+    ExecuteMsg::CreateEscrow {
+        recipient: recipient.to_string(),
+        arbiter: "arbiter".to_string(),
+        amount: Uint128::new(amount),
+        expiration: Expiration::AtHeight(mock_env().block.height + 10),
+    }
+}
+
+// escrow 1 pays 300 to seller, escrow 2 pays 200 to other
+fn do_create_escrows(mut deps: DepsMut) {
+    do_instantiate(deps.branch(), RECIPIENT, Uint128::new(1000));
+    let info = mock_info(RECIPIENT, &[]);
+    let msg = create_escrow("seller", 300);
+    execute(deps.branch(), mock_env(), info.clone(), msg).unwrap();
+    execute(deps, mock_env(), info, create_escrow("other", 200)).unwrap();
+}
+
+fn escrow_expired_env() -> Env {
+    let mut env = mock_env();
+    env.block.height += 10;
+    env
+}
+
+#[test]
+fn escrow_cannot_be_created_expired() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(1000));
+    let info = mock_info(RECIPIENT, &[]);
+    let msg = create_escrow("seller", 100);
+    let err = execute(deps.as_mut(), escrow_expired_env(), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::Expired {});
+}
+
+#[test]
+fn escrow_holds_the_tokens() {
+    let mut deps = mock_dependencies(&[]);
+    do_create_escrows(deps.as_mut());
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(500));
+    assert_eq!(
+        get_balance(deps.as_ref(), MOCK_CONTRACT_ADDR),
+        Uint128::new(500)
+    );
+    assert_eq!(escrows_by(deps.as_ref(), Some(RECIPIENT), None), vec![1, 2]);
+    assert_eq!(escrows_by(deps.as_ref(), None, Some("seller")), vec![1]);
+    let msg = QueryMsg::Escrow { id: 2 };
+    let res: EscrowResponse = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    let escrow = res.escrow.unwrap();
+    assert_eq!(escrow.recipient, Addr::unchecked("other"));
+    assert_eq!(escrow.amount, Uint128::new(200));
+}
+
+#[test]
+fn escrow_is_approved_by_the_arbiter() {
+    let mut deps = mock_dependencies(&[]);
+    do_create_escrows(deps.as_mut());
+    let approve = ExecuteMsg::ApproveEscrow { id: 1 };
+    let info = mock_info(RECIPIENT, &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, approve.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let arbiter = mock_info("arbiter", &[]);
+    execute(deps.as_mut(), mock_env(), arbiter.clone(), approve.clone()).unwrap();
+    assert_eq!(get_balance(deps.as_ref(), "seller"), Uint128::new(300));
+    let err = execute(deps.as_mut(), mock_env(), arbiter, approve).unwrap_err();
+    assert_eq!(err, ContractError::UnknownEscrow { id: 1 });
+}
+
+#[test]
+fn expired_escrow_is_refunded_to_the_sender() {
+    let mut deps = mock_dependencies(&[]);
+    do_create_escrows(deps.as_mut());
+    let refund = ExecuteMsg::RefundEscrow { id: 2 };
+    let info = mock_info("anyone", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), refund.clone()).unwrap_err();
+    assert_eq!(err, ContractError::EscrowNotExpired { id: 2 });
+
+    // once expired it can no longer be approved
+    let approve = ExecuteMsg::ApproveEscrow { id: 2 };
+    let arbiter = mock_info("arbiter", &[]);
+    let err = execute(deps.as_mut(), escrow_expired_env(), arbiter, approve).unwrap_err();
+    assert_eq!(err, ContractError::Expired {});
+    execute(deps.as_mut(), escrow_expired_env(), info, refund).unwrap();
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(700));
+    assert_eq!(get_balance(deps.as_ref(), "other"), Uint128::zero());
+    assert_eq!(
+        get_balance(deps.as_ref(), MOCK_CONTRACT_ADDR),
+        Uint128::new(300)
+    );
+    assert_eq!(escrows_by(deps.as_ref(), Some(RECIPIENT), None), vec![1]);
+}
+
+#[test]
+fn frozen_sender_gets_no_escrow_refund() {
+    let mut deps = mock_dependencies(&[]);
+    do_create_escrows(deps.as_mut());
+    let msg = ExecuteMsg::Freeze {
+        address: RECIPIENT.to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+
+    let refund = ExecuteMsg::RefundEscrow { id: 1 };
+    let info = mock_info("anyone", &[]);
+    let err = execute(deps.as_mut(), escrow_expired_env(), info, refund).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::FrozenRecipient {
+            address: RECIPIENT.to_string()
+        }
+    );
+    assert_eq!(
+        get_balance(deps.as_ref(), MOCK_CONTRACT_ADDR),
+        Uint128::new(500)
+    );
+}
+
+fn query_stream(deps: Deps, env: Env, id: u64) -> StreamResponse {
+    from_binary(&query(deps, env, QueryMsg::Stream { id }).unwrap()).unwrap()
+}
+
+// stream 1 pays 600 to worker between 1_000 and 1_600
+fn do_create_stream(mut deps: DepsMut) {
+    do_instantiate(deps.branch(), RECIPIENT, Uint128::new(1200));
+    let msg = ExecuteMsg::CreateStream {
+        recipient: "worker".to_string(),
+        amount: Uint128::new(600),
+        start: 1_000,
+        end: 1_600,
+    };
+    execute(deps, env_at(900), mock_info(RECIPIENT, &[]), msg).unwrap();
+}
+
+#[test]
+fn stream_must_end_in_the_future() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(1200));
+    let msg = ExecuteMsg::CreateStream {
+        recipient: "worker".to_string(),
+        amount: Uint128::new(600),
+        start: 1_000,
+        end: 1_600,
+    };
+    let info = mock_info(RECIPIENT, &[]);
+    let err = execute(deps.as_mut(), env_at(1_600), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidStream {});
+}
+
+#[test]
+fn streams_pay_out_linearly() {
+    let mut deps = mock_dependencies(&[]);
+    do_create_stream(deps.as_mut());
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(600));
+
+    // nothing is earned before start
+    let withdraw = ExecuteMsg::WithdrawFromStream { id: 1 };
+    let worker = mock_info("worker", &[]);
+    let err = execute(
+        deps.as_mut(),
+        env_at(1_000),
+        worker.clone(),
+        withdraw.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NothingToClaim {});
+    let info = mock_info(RECIPIENT, &[]);
+    let err = execute(deps.as_mut(), env_at(1_100), info, withdraw.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    execute(deps.as_mut(), env_at(1_100), worker, withdraw).unwrap();
+    assert_eq!(get_balance(deps.as_ref(), "worker"), Uint128::new(100));
+    let res = query_stream(deps.as_ref(), env_at(1_300), 1);
+    assert_eq!(res.streamed, Uint128::new(300));
+    assert_eq!(res.withdrawable, Uint128::new(200));
+    assert_eq!(res.stream.unwrap().withdrawn, Uint128::new(100));
+}
+
+#[test]
+fn cancelled_stream_pays_what_was_earned() {
+    let mut deps = mock_dependencies(&[]);
+    do_create_stream(deps.as_mut());
+    let cancel = ExecuteMsg::CancelStream { id: 1 };
+    let worker = mock_info("worker", &[]);
+    let err = execute(deps.as_mut(), env_at(1_300), worker.clone(), cancel.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    execute(
+        deps.as_mut(),
+        env_at(1_300),
+        mock_info(RECIPIENT, &[]),
+        cancel,
+    )
+    .unwrap();
+    assert_eq!(get_balance(deps.as_ref(), "worker"), Uint128::new(300));
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(900));
+    assert_eq!(query_stream(deps.as_ref(), env_at(1_300), 1).stream, None);
+    let withdraw = ExecuteMsg::WithdrawFromStream { id: 1 };
+    let err = execute(deps.as_mut(), env_at(1_300), worker, withdraw).unwrap_err();
+    assert_eq!(err, ContractError::UnknownStream { id: 1 });
+}
+
+#[test]
+fn stream_withdrawn_in_full_is_closed() {
+    let mut deps = mock_dependencies(&[]);
+    do_create_stream(deps.as_mut());
+    let withdraw = ExecuteMsg::WithdrawFromStream { id: 1 };
+    execute(
+        deps.as_mut(),
+        env_at(2_000),
+        mock_info("worker", &[]),
+        withdraw,
+    )
+    .unwrap();
+    assert_eq!(get_balance(deps.as_ref(), "worker"), Uint128::new(600));
+    assert_eq!(
+        get_balance(deps.as_ref(), MOCK_CONTRACT_ADDR),
+        Uint128::zero()
+    );
+    assert_eq!(query_stream(deps.as_ref(), env_at(2_000), 1).stream, None);
+}
+
+#[test]
+fn cancel_stream_pays_no_frozen_party() {
+    let mut deps = mock_dependencies(&[]);
+    do_create_stream(deps.as_mut());
+    let cancel = ExecuteMsg::CancelStream { id: 1 };
+
+    // neither the earned part nor the refund goes to a frozen account
+    for frozen in ["worker", RECIPIENT] {
+        let admin = mock_info(INIT_ADDRESS, &[]);
+        let freeze = ExecuteMsg::Freeze {
+            address: frozen.to_string(),
+        };
+        execute(deps.as_mut(), env_at(1_300), admin.clone(), freeze).unwrap();
+        let info = mock_info(RECIPIENT, &[]);
+        let err = execute(deps.as_mut(), env_at(1_300), info, cancel.clone()).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::FrozenRecipient {
+                address: frozen.to_string()
+            }
+        );
+        let unfreeze = ExecuteMsg::Unfreeze {
+            address: frozen.to_string(),
+        };
+        execute(deps.as_mut(), env_at(1_300), admin, unfreeze).unwrap();
+    }
+
+    let info = mock_info(RECIPIENT, &[]);
+    execute(deps.as_mut(), env_at(1_300), info, cancel).unwrap();
+    assert_eq!(get_balance(deps.as_ref(), "worker"), Uint128::new(300));
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(900));
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+fn hash_pair(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    sha256(&[first, second].concat())
+}
+
+// a tree of three leaves: root = H(H(l0, l1), l2), returns the root and the
+// proof of every leaf
+fn merkle_fixture(claims: &[(&str, u128); 3]) -> (String, Vec<Vec<String>>) {
+    let leaves: Vec<[u8; 32]> = claims
+        .iter()
+        .map(|(address, amount)| sha256(format!("{}{}", address, amount).as_bytes()))
+        .collect();
+    let inner = hash_pair(leaves[0], leaves[1]);
+    let root = hash_pair(inner, leaves[2]);
+    let proofs = vec![
+        vec![hex::encode(leaves[1]), hex::encode(leaves[2])],
+        vec![hex::encode(leaves[0]), hex::encode(leaves[2])],
+        vec![hex::encode(inner)],
+    ];
+    (hex::encode(root), proofs)
+}
+
+fn register_stage(root: &str) -> ExecuteMsg {
+    ExecuteMsg::RegisterMerkleRoot {
+        stage: 1,
+        root: root.to_string(),
+        expiration: Expiration::AtHeight(mock_env().block.height + 100),
+        total_amount: Uint128::new(600),
+    }
+}
+
+fn claim_airdrop(amount: u128, proof: &[String]) -> ExecuteMsg {
+    ExecuteMsg::ClaimAirdrop {
+        stage: 1,
+        amount: Uint128::new(amount),
+        proof: proof.to_vec(),
+    }
+}
+
+// stage 1 airdrops 100 to alice, 200 to bob and 300 to carol, returns their proofs
+fn do_register_airdrop(mut deps: DepsMut) -> Vec<Vec<String>> {
+    let cap = Some(Uint128::new(1600));
+    do_instantiate_with_minter(deps.branch(), RECIPIENT, Uint128::new(1000), MINTER, cap);
+    let (root, proofs) = merkle_fixture(&[("alice", 100), ("bob", 200), ("carol", 300)]);
+    let admin = mock_info(INIT_ADDRESS, &[]);
+    execute(deps, mock_env(), admin, register_stage(&root)).unwrap();
+    proofs
+}
+
+fn airdrop_expired_env() -> Env {
+    let mut env = mock_env();
+    env.block.height += 100;
+    env
+}
+
+#[test]
+fn admin_registers_merkle_roots() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(1000));
+    let (root, _) = merkle_fixture(&[("alice", 100), ("bob", 200), ("carol", 300)]);
+    let info = mock_info("alice", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, register_stage(&root)).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Admin(cw_controllers::AdminError::NotAdmin {})
+    );
+    let admin = mock_info(INIT_ADDRESS, &[]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        admin.clone(),
+        register_stage("abcd"),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidMerkleRoot {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        admin.clone(),
+        register_stage(&root),
+    )
+    .unwrap();
+    let err = execute(deps.as_mut(), mock_env(), admin, register_stage(&root)).unwrap_err();
+    assert_eq!(err, ContractError::StageExists { stage: 1 });
+}
+
+#[test]
+fn airdrop_claims_need_a_valid_proof() {
+    let mut deps = mock_dependencies(&[]);
+    let proofs = do_register_airdrop(deps.as_mut());
+    // a wrong amount or someone else's proof does not verify
+    let alice = mock_info("alice", &[]);
+    let msg = claim_airdrop(101, &proofs[0]);
+    let err = execute(deps.as_mut(), mock_env(), alice.clone(), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidProof {});
+    let msg = claim_airdrop(200, &proofs[1]);
+    let err = execute(deps.as_mut(), mock_env(), alice, msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidProof {});
+    // nor can anything be claimed after expiry
+    let msg = claim_airdrop(200, &proofs[1]);
+    let bob = mock_info("bob", &[]);
+    let err = execute(deps.as_mut(), airdrop_expired_env(), bob, msg).unwrap_err();
+    assert_eq!(err, ContractError::Expired {});
+}
+
+#[test]
+fn merkle_airdrop_claims_mint_once() {
+    let mut deps = mock_dependencies(&[]);
+    let proofs = do_register_airdrop(deps.as_mut());
+    let alice = mock_info("alice", &[]);
+    let msg = claim_airdrop(100, &proofs[0]);
+    execute(deps.as_mut(), mock_env(), alice.clone(), msg.clone()).unwrap();
+    let carol = mock_info("carol", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        carol,
+        claim_airdrop(300, &proofs[2]),
+    )
+    .unwrap();
+    let err = execute(deps.as_mut(), mock_env(), alice, msg).unwrap_err();
+    assert_eq!(err, ContractError::AirdropClaimed { stage: 1 });
+
+    assert_eq!(get_balance(deps.as_ref(), "alice"), Uint128::new(100));
+    assert_eq!(get_balance(deps.as_ref(), "carol"), Uint128::new(300));
+    assert_eq!(
+        query_token_info(deps.as_ref()).unwrap().total_supply,
+        Uint128::new(1400)
+    );
+    let msg = QueryMsg::AirdropClaimed {
+        stage: 1,
+        address: "alice".to_string(),
+    };
+    let res: AirdropClaimedResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert!(res.claimed);
+}
+
+#[test]
+fn unclaimed_airdrop_is_reclaimed_after_expiry() {
+    let mut deps = mock_dependencies(&[]);
+    let proofs = do_register_airdrop(deps.as_mut());
+    let msg = claim_airdrop(100, &proofs[0]);
+    execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+
+    // the unclaimed part goes to the admin, within the cap
+    let reclaim = ExecuteMsg::ReclaimAirdrop {
+        stage: 1,
+        recipient: None,
+    };
+    let admin = mock_info(INIT_ADDRESS, &[]);
+    let err = execute(deps.as_mut(), mock_env(), admin.clone(), reclaim.clone()).unwrap_err();
+    assert_eq!(err, ContractError::StageNotExpired { stage: 1 });
+    let later = airdrop_expired_env();
+    execute(deps.as_mut(), later.clone(), admin.clone(), reclaim.clone()).unwrap();
+    assert_eq!(get_balance(deps.as_ref(), INIT_ADDRESS), Uint128::new(500));
+    let err = execute(deps.as_mut(), later.clone(), admin, reclaim).unwrap_err();
+    assert_eq!(err, ContractError::NothingToClaim {});
+    let msg = QueryMsg::MerkleStage { stage: 1 };
+    let res: MerkleStageResponse = from_binary(&query(deps.as_ref(), later, msg).unwrap()).unwrap();
+    assert_eq!(res.claimed, Uint128::new(100));
+    assert!(res.reclaimed);
+}
+
+fn failed_withdrawals(deps: Deps, address: &str) -> Vec<FailedWithdrawal> {
+    let msg = QueryMsg::FailedWithdrawals {
+        address: address.to_string(),
+        start_after: None,
+        limit: None,
+    };
+    let res: FailedWithdrawalsResponse =
+        from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+    res.withdrawals
+}
+
+fn lucky_withdrawal() -> BankMsg {
+    BankMsg::Send {
+        to_address: "lucky".to_string(),
+        amount: vec![Coin::new(200, "uluna")],
+    }
+}
+
+fn withdrawal_sent() -> Reply {
+    Reply {
+        id: WITHDRAW_REPLY_ID,
+        result: ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    }
+}
+
+// RECIPIENT withdraws 200 of 500 uluna to lucky, and the bank send fails
+fn do_fail_withdrawal(mut deps: DepsMut) {
+    do_instantiate(deps.branch(), RECIPIENT, Uint128::new(100));
+    do_deposit(deps.branch(), RECIPIENT, "uluna", 500);
+    let msg = ExecuteMsg::Withdraw {
+        denom: "uluna".to_string(),
+        amount: Uint128::new(200),
+        recipient: Some("lucky".to_string()),
+    };
+    execute(deps.branch(), mock_env(), mock_info(RECIPIENT, &[]), msg).unwrap();
+    let failure = Reply {
+        id: WITHDRAW_REPLY_ID,
+        result: ContractResult::Err("blocked address".to_string()),
+    };
+    reply(deps, mock_env(), failure).unwrap();
+}
+
+#[test]
+fn withdrawals_reply_with_the_send_outcome() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(100));
+    do_deposit(deps.as_mut(), RECIPIENT, "uluna", 500);
+    let msg = ExecuteMsg::Withdraw {
+        denom: "uluna".to_string(),
+        amount: Uint128::new(200),
+        recipient: Some("lucky".to_string()),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(RECIPIENT, &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(lucky_withdrawal(), WITHDRAW_REPLY_ID)]
+    );
+    assert_eq!(
+        get_denom_balance(deps.as_ref(), RECIPIENT, "uluna"),
+        Uint128::new(300)
+    );
+
+    reply(deps.as_mut(), mock_env(), withdrawal_sent()).unwrap();
+    // the pending withdrawal is cleared
+    let err = reply(deps.as_mut(), mock_env(), withdrawal_sent()).unwrap_err();
+    assert!(matches!(err, ContractError::Std(StdError::NotFound { .. })));
+    let unknown = Reply {
+        id: 42,
+        result: ContractResult::Err("oops".to_string()),
+    };
+    let err = reply(deps.as_mut(), mock_env(), unknown).unwrap_err();
+    assert_eq!(err, ContractError::UnknownReplyId { id: 42 });
+}
+
+#[test]
+fn failed_withdrawals_are_restored() {
+    let mut deps = mock_dependencies(&[]);
+    do_fail_withdrawal(deps.as_mut());
+    // the amount goes back to the balance and is queued
+    assert_eq!(
+        get_denom_balance(deps.as_ref(), RECIPIENT, "uluna"),
+        Uint128::new(500)
+    );
+    let failed = FailedWithdrawal {
+        id: 1,
+        recipient: Addr::unchecked("lucky"),
+        denom: "uluna".to_string(),
+        amount: Uint128::new(200),
+        error: "blocked address".to_string(),
+    };
+    assert_eq!(failed_withdrawals(deps.as_ref(), RECIPIENT), vec![failed]);
+    assert_eq!(failed_withdrawals(deps.as_ref(), "lucky"), vec![]);
+}
+
+#[test]
+fn failed_withdrawals_are_retried_once_by_the_owner() {
+    let mut deps = mock_dependencies(&[]);
+    do_fail_withdrawal(deps.as_mut());
+    let retry = ExecuteMsg::RetryWithdrawal { id: 1 };
+    let info = mock_info("lucky", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, retry.clone()).unwrap_err();
+    assert_eq!(err, ContractError::UnknownFailedWithdrawal { id: 1 });
+
+    let owner = mock_info(RECIPIENT, &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner.clone(), retry.clone()).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(lucky_withdrawal(), WITHDRAW_REPLY_ID)]
+    );
+    reply(deps.as_mut(), mock_env(), withdrawal_sent()).unwrap();
+    assert_eq!(
+        get_denom_balance(deps.as_ref(), RECIPIENT, "uluna"),
+        Uint128::new(300)
+    );
+    assert_eq!(failed_withdrawals(deps.as_ref(), RECIPIENT), vec![]);
+    let err = execute(deps.as_mut(), mock_env(), owner, retry).unwrap_err();
+    assert_eq!(err, ContractError::UnknownFailedWithdrawal { id: 1 });
+}
+
+#[test]
+fn frozen_accounts_cannot_move_funds() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(100));
+    do_deposit(deps.as_mut(), RECIPIENT, "uluna", 500);
+
+    // only the admin or the officer may freeze
+    let msg = ExecuteMsg::Freeze {
+        address: RECIPIENT.to_string(),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+
+    let info = mock_info(RECIPIENT, &[]);
+    let frozen_sender = ContractError::FrozenSender {
+        address: RECIPIENT.to_string(),
+    };
+    let msg = ExecuteMsg::Transfer {
+        recipient: "lucky".to_string(),
+        amount: Uint128::new(10),
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(err, frozen_sender);
+    let msg = ExecuteMsg::Send {
+        contract: "lucky".to_string(),
+        amount: Uint128::new(10),
+        msg: Binary::default(),
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(err, frozen_sender);
+    let msg = ExecuteMsg::Withdraw {
+        denom: "uluna".to_string(),
+        amount: Uint128::new(10),
+        recipient: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err, frozen_sender);
+
+    // nor can the frozen account receive
+    do_deposit(deps.as_mut(), "lucky", "uluna", 500);
+    let frozen_recipient = ContractError::FrozenRecipient {
+        address: RECIPIENT.to_string(),
+    };
+    let msg = ExecuteMsg::Withdraw {
+        denom: "uluna".to_string(),
+        amount: Uint128::new(10),
+        recipient: Some(RECIPIENT.to_string()),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("lucky", &[]), msg).unwrap_err();
+    assert_eq!(err, frozen_recipient);
+    let info = mock_info(RECIPIENT, &[Coin::new(10, "uluna")]);
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {}).unwrap_err();
+    assert_eq!(err, frozen_recipient);
+
+    // unfreezing restores access
+    let msg = ExecuteMsg::Unfreeze {
+        address: RECIPIENT.to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+    let msg = ExecuteMsg::Transfer {
+        recipient: "lucky".to_string(),
+        amount: Uint128::new(10),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(RECIPIENT, &[]), msg).unwrap();
+    assert_eq!(get_balance(deps.as_ref(), "lucky"), Uint128::new(10));
+}
+
+#[test]
+fn frozen_recipient_cannot_receive_tokens() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate_with_minter(deps.as_mut(), RECIPIENT, Uint128::new(100), MINTER, None);
+    let msg = ExecuteMsg::Freeze {
+        address: "lucky".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+
+    let frozen_recipient = ContractError::FrozenRecipient {
+        address: "lucky".to_string(),
+    };
+    let msg = ExecuteMsg::Transfer {
+        recipient: "lucky".to_string(),
+        amount: Uint128::new(10),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info(RECIPIENT, &[]), msg).unwrap_err();
+    assert_eq!(err, frozen_recipient);
+    let msg = ExecuteMsg::Mint {
+        recipient: "lucky".to_string(),
+        amount: Uint128::new(10),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info(MINTER, &[]), msg).unwrap_err();
+    assert_eq!(err, frozen_recipient);
+}
+
+#[test]
+fn officer_can_seize_to_recovery_address() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(100));
+    do_deposit(deps.as_mut(), RECIPIENT, "uluna", 500);
+
+    let officer = mock_info("officer", &[]);
+    let seize = ExecuteMsg::Seize {
+        address: RECIPIENT.to_string(),
+        denom: None,
+        amount: Uint128::new(60),
+    };
+    let err = execute(deps.as_mut(), mock_env(), officer.clone(), seize.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // the officer alone cannot configure compliance
+    let msg = ExecuteMsg::UpdateCompliance {
+        officer: Some("officer".to_string()),
+        recovery_address: None,
+    };
+    execute(deps.as_mut(), mock_env(), officer.clone(), msg.clone()).unwrap_err();
+    execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+
+    // seizure is disabled until there is a recovery address
+    let err = execute(deps.as_mut(), mock_env(), officer.clone(), seize.clone()).unwrap_err();
+    assert_eq!(err, ContractError::NoRecoveryAddress {});
+    let msg = ExecuteMsg::UpdateCompliance {
+        officer: Some("officer".to_string()),
+        recovery_address: Some("treasury".to_string()),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+
+    execute(deps.as_mut(), mock_env(), officer.clone(), seize).unwrap();
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(40));
+    assert_eq!(get_balance(deps.as_ref(), "treasury"), Uint128::new(60));
+
+    let seize = ExecuteMsg::Seize {
+        address: RECIPIENT.to_string(),
+        denom: Some("uluna".to_string()),
+        amount: Uint128::new(500),
+    };
+    execute(deps.as_mut(), mock_env(), officer, seize).unwrap();
+    assert_eq!(
+        get_denom_balance(deps.as_ref(), RECIPIENT, "uluna"),
+        Uint128::zero()
+    );
+    assert_eq!(
+        get_denom_balance(deps.as_ref(), "treasury", "uluna"),
+        Uint128::new(500)
+    );
+}
+
+#[test]
+fn query_frozen_accounts_paginates() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(100));
+    for address in ["carol", "alice", "bob"] {
+        let msg = ExecuteMsg::Freeze {
+            address: address.to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+    }
+
+    let msg = QueryMsg::FrozenAccounts {
+        start_after: None,
+        limit: Some(2),
+    };
+    let res: FrozenAccountsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.accounts, vec!["alice", "bob"]);
+
+    let msg = QueryMsg::FrozenAccounts {
+        start_after: Some("bob".to_string()),
+        limit: None,
+    };
+    let res: FrozenAccountsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.accounts, vec!["carol"]);
+}
+
+/// MockApi can only humanize its own 54 byte canonical addresses, while permits
+/// derive 20 byte ones from the owner public key. Those are rendered as hex.
+#[derive(Copy, Clone, Default)]
+struct PermitApi(MockApi);
+
+impl Api for PermitApi {
+    fn addr_validate(&self, human: &str) -> StdResult<Addr> {
+        self.0.addr_validate(human)
+    }
+
+    fn addr_canonicalize(&self, human: &str) -> StdResult<CanonicalAddr> {
+        self.0.addr_canonicalize(human)
+    }
+
+    fn addr_humanize(&self, canonical: &CanonicalAddr) -> StdResult<Addr> {
+        if canonical.len() != 20 {
+            return self.0.addr_humanize(canonical);
+        }
+        let hex: String = canonical
+            .as_slice()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        Ok(Addr::unchecked(hex))
+    }
+
+    fn secp256k1_verify(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        self.0.secp256k1_verify(message_hash, signature, public_key)
+    }
+
+    fn secp256k1_recover_pubkey(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        recovery_param: u8,
+    ) -> Result<Vec<u8>, RecoverPubkeyError> {
+        self.0
+            .secp256k1_recover_pubkey(message_hash, signature, recovery_param)
+    }
+
+    fn ed25519_verify(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        self.0.ed25519_verify(message, signature, public_key)
+    }
+
+    fn ed25519_batch_verify(
+        &self,
+        messages: &[&[u8]],
+        signatures: &[&[u8]],
+        public_keys: &[&[u8]],
+    ) -> Result<bool, VerificationError> {
+        self.0
+            .ed25519_batch_verify(messages, signatures, public_keys)
+    }
+
+    fn debug(&self, message: &str) {
+        self.0.debug(message)
+    }
+}
+
+fn mock_permit_dependencies() -> OwnedDeps<MockStorage, PermitApi, MockQuerier> {
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: PermitApi::default(),
+        querier: MockQuerier::new(&[]),
+    }
+}
+
+fn sign_permit(key: &SigningKey, permit: &PermitMsg) -> Binary {
+    let signature: Signature = key.sign(&cosmwasm_std::to_vec(permit).unwrap());
+    Binary::from(signature.as_ref())
+}
+
+fn permit_msg(spender: &str, amount: u128, nonce: u64) -> PermitMsg {
+    let env = mock_env();
+    PermitMsg {
+        chain_id: env.block.chain_id,
+        contract: env.contract.address.to_string(),
+        spender: spender.to_string(),
+        amount: Uint128::new(amount),
+        expires: Expiration::Never {},
+        nonce,
+    }
+}
+
+fn submit_permit(
+    deps: DepsMut,
+    key: &SigningKey,
+    permit: &PermitMsg,
+) -> Result<cosmwasm_std::Response, ContractError> {
+    let msg = ExecuteMsg::Permit {
+        owner_pubkey: Binary::from(key.verifying_key().to_bytes().as_slice()),
+        spender: permit.spender.clone(),
+        amount: permit.amount,
+        expires: permit.expires,
+        nonce: permit.nonce,
+        signature: sign_permit(key, permit),
+    };
+    execute(deps, mock_env(), mock_info("relayer", &[]), msg)
+}
+
+#[test]
+fn permit_sets_allowance_for_spender() {
+    let mut deps = mock_permit_dependencies();
+    let key = SigningKey::from_bytes(&[7u8; 32]).unwrap();
+    let pubkey = key.verifying_key().to_bytes();
+    let owner = pubkey_to_address(deps.as_ref(), pubkey.as_slice()).unwrap();
+    do_instantiate(deps.as_mut(), owner.as_str(), Uint128::new(100));
+
+    let permit = permit_msg("spender", 60, 0);
+    submit_permit(deps.as_mut(), &key, &permit).unwrap();
+
+    let msg = QueryMsg::Allowance {
+        owner: owner.to_string(),
+        spender: "spender".to_string(),
+    };
+    let res: AllowanceResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.allowance, Uint128::new(60));
+
+    // the spender can use it without the owner ever paying gas
+    let msg = ExecuteMsg::TransferFrom {
+        owner: owner.to_string(),
+        recipient: "lucky".to_string(),
+        amount: Uint128::new(60),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("spender", &[]), msg).unwrap();
+    assert_eq!(get_balance(deps.as_ref(), owner.as_str()), Uint128::new(40));
+    assert_eq!(get_balance(deps.as_ref(), "lucky"), Uint128::new(60));
+
+    // the same permit cannot be replayed
+    let err = submit_permit(deps.as_mut(), &key, &permit).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidNonce {
+            expected: 1,
+            got: 0
+        }
+    );
+    let msg = QueryMsg::PermitNonce {
+        owner: owner.to_string(),
+    };
+    let res: PermitNonceResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.nonce, 1);
+}
+
+#[test]
+fn permit_rejects_foreign_domain_and_tampering() {
+    let mut deps = mock_permit_dependencies();
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(100));
+    let key = SigningKey::from_bytes(&[7u8; 32]).unwrap();
+
+    // signed for another chain
+    let mut permit = permit_msg("spender", 60, 0);
+    permit.chain_id = "other-chain".to_string();
+    let msg = ExecuteMsg::Permit {
+        owner_pubkey: Binary::from(key.verifying_key().to_bytes().as_slice()),
+        spender: permit.spender.clone(),
+        amount: permit.amount,
+        expires: permit.expires,
+        nonce: permit.nonce,
+        signature: sign_permit(&key, &permit),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("relayer", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidSignature {});
+
+    // signed for a smaller amount than submitted
+    let permit = permit_msg("spender", 60, 0);
+    let msg = ExecuteMsg::Permit {
+        owner_pubkey: Binary::from(key.verifying_key().to_bytes().as_slice()),
+        spender: permit.spender.clone(),
+        amount: Uint128::new(1000),
+        expires: permit.expires,
+        nonce: permit.nonce,
+        signature: sign_permit(&key, &permit),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("relayer", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidSignature {});
+
+    // already expired
+    let mut permit = permit_msg("spender", 60, 0);
+    permit.expires = Expiration::AtHeight(mock_env().block.height);
+    let err = submit_permit(deps.as_mut(), &key, &permit).unwrap_err();
+    assert_eq!(err, ContractError::Expired {});
+}
+
+fn flash_loan(denom: &str, amount: u128) -> ExecuteMsg {
+    ExecuteMsg::FlashLoan {
+        denom: denom.to_string(),
+        amount: Uint128::new(amount),
+        callback_contract: "borrower".to_string(),
+        msg: Binary::from(b"{}".as_ref()),
+    }
+}
+
+// 10_000 uluna in reserve, lent at a 1% fee
+fn do_setup_flash_loans(mut deps: DepsMut) {
+    do_instantiate(deps.branch(), RECIPIENT, Uint128::new(100));
+    let admin = mock_info(INIT_ADDRESS, &[]);
+    let msg = ExecuteMsg::WhiteListCoin {
+        denom: "uluna".to_string(),
+        status: true,
+    };
+    execute(deps.branch(), mock_env(), admin.clone(), msg).unwrap();
+    let msg = ExecuteMsg::UpdateFlashLoanFee {
+        fee: Decimal::percent(1),
+    };
+    execute(deps, mock_env(), admin, msg).unwrap();
+}
+
+// settles the open loan with the contract holding balance uluna
+fn finish_flash_loan(
+    deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    balance: u128,
+) -> Result<Response, ContractError> {
+    let contract = mock_env().contract.address;
+    deps.querier
+        .update_balance(contract.clone(), vec![Coin::new(balance, "uluna")]);
+    let info = mock_info(contract.as_str(), &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::FinishFlashLoan {},
+    )
+}
+
+#[test]
+fn admin_sets_the_flash_loan_fee() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(100));
+    let msg = ExecuteMsg::UpdateFlashLoanFee {
+        fee: Decimal::percent(1),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Admin(cw_controllers::AdminError::NotAdmin {})
+    );
+    execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+    let res: FlashLoanFeeResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FlashLoanFee {}).unwrap()).unwrap();
+    assert_eq!(res.fee, Decimal::percent(1));
+}
+
+#[test]
+fn flash_loan_lends_from_the_reserves() {
+    let mut deps = mock_dependencies(&[Coin::new(10_000, "uluna")]);
+    do_setup_flash_loans(deps.as_mut());
+    let borrower = mock_info("borrower", &[]);
+    let msg = flash_loan("uluna", 20_000);
+    let err = execute(deps.as_mut(), mock_env(), borrower.clone(), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InsufficientReserves {
+            denom: "uluna".to_string(),
+            available: Uint128::new(10_000),
+        }
+    );
+
+    let msg = flash_loan("uluna", 5_000);
+    let res = execute(deps.as_mut(), mock_env(), borrower, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: "borrower".to_string(),
+                msg: Binary::from(b"{}".as_ref()),
+                funds: vec![Coin::new(5_000, "uluna")],
+            }),
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: mock_env().contract.address.to_string(),
+                msg: to_binary(&ExecuteMsg::FinishFlashLoan {}).unwrap(),
+                funds: vec![],
+            }),
+        ]
+    );
+}
+
+#[test]
+fn open_flash_loan_blocks_deposits_and_nested_loans() {
+    let mut deps = mock_dependencies(&[Coin::new(10_000, "uluna")]);
+    do_setup_flash_loans(deps.as_mut());
+    let borrower = mock_info("borrower", &[]);
+    let msg = flash_loan("uluna", 5_000);
+    execute(deps.as_mut(), mock_env(), borrower.clone(), msg).unwrap();
+
+    let info = mock_info("borrower", &[Coin::new(5_000, "uluna")]);
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {}).unwrap_err();
+    assert_eq!(err, ContractError::FlashLoanInProgress {});
+    let msg = flash_loan("uluna", 1);
+    let err = execute(deps.as_mut(), mock_env(), borrower.clone(), msg).unwrap_err();
+    assert_eq!(err, ContractError::FlashLoanInProgress {});
+    // only the contract itself may settle
+    let msg = ExecuteMsg::FinishFlashLoan {};
+    let err = execute(deps.as_mut(), mock_env(), borrower, msg).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+}
+
+#[test]
+fn flash_loan_requires_repayment_with_fee() {
+    let mut deps = mock_dependencies(&[Coin::new(10_000, "uluna")]);
+    do_setup_flash_loans(deps.as_mut());
+    let borrower = mock_info("borrower", &[]);
+    let msg = flash_loan("uluna", 5_000);
+    execute(deps.as_mut(), mock_env(), borrower.clone(), msg).unwrap();
+
+    let err = finish_flash_loan(&mut deps, 10_000).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::FlashLoanNotRepaid {
+            expected: Uint128::new(10_050),
+            actual: Uint128::new(10_000),
+        }
+    );
+    finish_flash_loan(&mut deps, 10_050).unwrap();
+
+    // the loan is settled, so a new one can be taken
+    execute(deps.as_mut(), mock_env(), borrower, flash_loan("uluna", 1)).unwrap();
+}
+
+#[test]
+fn flash_loan_fees_are_booked_for_the_admin() {
+    let mut deps = mock_dependencies(&[Coin::new(10_000, "uluna")]);
+    do_setup_flash_loans(deps.as_mut());
+    let msg = flash_loan("uluna", 5_000);
+    execute(deps.as_mut(), mock_env(), mock_info("borrower", &[]), msg).unwrap();
+    finish_flash_loan(&mut deps, 10_050).unwrap();
+    let res: FlashLoanFeeResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FlashLoanFee {}).unwrap()).unwrap();
+    assert_eq!(res.collected, vec![Coin::new(50, "uluna")]);
+
+    let msg = ExecuteMsg::WithdrawFlashLoanFees {
+        denom: "uluna".to_string(),
+        recipient: Some("treasury".to_string()),
+    };
+    let info = mock_info("anyone", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Admin(cw_controllers::AdminError::NotAdmin {})
+    );
+
+    let admin = mock_info(INIT_ADDRESS, &[]);
+    let res = execute(deps.as_mut(), mock_env(), admin.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: vec![Coin::new(50, "uluna")],
+        })]
+    );
+    let err = execute(deps.as_mut(), mock_env(), admin, msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::NoFlashLoanFees {
+            denom: "uluna".to_string()
+        }
+    );
+}
+
+#[test]
+fn only_whitelisted_reserves_can_be_borrowed() {
+    let mut deps = mock_dependencies(&[Coin::new(10_000, "uluna"), Coin::new(500, "uatom")]);
+    do_setup_flash_loans(deps.as_mut());
+    let borrower = mock_info("borrower", &[]);
+    let msg = flash_loan("uatom", 500);
+    let err = execute(deps.as_mut(), mock_env(), borrower.clone(), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err("the coin is not whitelisted"))
+    );
+
+    // the booked fees stay in the contract but are not lent out
+    let msg = flash_loan("uluna", 5_000);
+    execute(deps.as_mut(), mock_env(), borrower.clone(), msg).unwrap();
+    finish_flash_loan(&mut deps, 10_050).unwrap();
+    let msg = flash_loan("uluna", 10_050);
+    let err = execute(deps.as_mut(), mock_env(), borrower.clone(), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InsufficientReserves {
+            denom: "uluna".to_string(),
+            available: Uint128::new(10_000),
+        }
+    );
+    let msg = flash_loan("uluna", 10_000);
+    execute(deps.as_mut(), mock_env(), borrower, msg).unwrap();
+}
+
+fn do_add_hook(deps: DepsMut, addr: &str) {
+    let msg = ExecuteMsg::AddHook {
+        addr: addr.to_string(),
+    };
+    execute(deps, mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+}
+
+fn token_diff(address: &str, old: u128, new: u128) -> BalanceDiff {
+    BalanceDiff {
+        address: address.to_string(),
+        denom: None,
+        old: Uint128::new(old),
+        new: Uint128::new(new),
+    }
+}
+
+#[test]
+fn admin_registers_hooks_once() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(100));
+    let msg = ExecuteMsg::AddHook {
+        addr: "rewards".to_string(),
+    };
+    let info = mock_info("anyone", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Hook(cw_controllers::HookError::Admin(
+            cw_controllers::AdminError::NotAdmin {}
+        ))
+    );
+    let admin = mock_info(INIT_ADDRESS, &[]);
+    execute(deps.as_mut(), mock_env(), admin.clone(), msg.clone()).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), admin, msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Hook(cw_controllers::HookError::HookAlreadyRegistered {})
+    );
+    do_add_hook(deps.as_mut(), "governance");
+    let res: HooksResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Hooks {}).unwrap()).unwrap();
+    assert_eq!(res.hooks, vec!["rewards", "governance"]);
+}
+
+#[test]
+fn hooks_are_notified_of_balance_changes() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(100));
+    do_add_hook(deps.as_mut(), "rewards");
+    do_add_hook(deps.as_mut(), "governance");
+    let msg = ExecuteMsg::Transfer {
+        recipient: "lucky".to_string(),
+        amount: Uint128::new(60),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(RECIPIENT, &[]), msg).unwrap();
+    let hook_msg = BalanceChangedHookMsg {
+        diffs: vec![token_diff(RECIPIENT, 100, 40), token_diff("lucky", 0, 60)],
+    };
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(hook_msg.clone().into_cosmos_msg("rewards").unwrap()),
+            SubMsg::new(hook_msg.into_cosmos_msg("governance").unwrap()),
+        ]
+    );
+}
+
+#[test]
+fn hooks_are_notified_of_mints_and_burns() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate_with_minter(deps.as_mut(), RECIPIENT, Uint128::new(100), MINTER, None);
+    do_add_hook(deps.as_mut(), "rewards");
+    do_add_hook(deps.as_mut(), "governance");
+    // removed hooks are no longer notified
+    let msg = ExecuteMsg::RemoveHook {
+        addr: "governance".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::Mint {
+        recipient: "lucky".to_string(),
+        amount: Uint128::new(5),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(MINTER, &[]), msg).unwrap();
+    let hook_msg = BalanceChangedHookMsg {
+        diffs: vec![token_diff("lucky", 0, 5)],
+    };
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(hook_msg.into_cosmos_msg("rewards").unwrap())]
+    );
+
+    let msg = ExecuteMsg::Burn {
+        amount: Uint128::new(5),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("lucky", &[]), msg).unwrap();
+    let hook_msg = BalanceChangedHookMsg {
+        diffs: vec![token_diff("lucky", 5, 0)],
+    };
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(hook_msg.into_cosmos_msg("rewards").unwrap())]
+    );
+    assert_eq!(
+        query_token_info(deps.as_ref()).unwrap().total_supply,
+        Uint128::new(100)
+    );
+}
+
+#[test]
+fn hooks_are_notified_of_native_balance_changes() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(100));
+    let msg = ExecuteMsg::AddHook {
+        addr: "rewards".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+    let msg = ExecuteMsg::WhiteListCoin {
+        denom: "uluna".to_string(),
+        status: true,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+
+    let info = mock_info(RECIPIENT, &[Coin::new(500, "uluna")]);
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {}).unwrap();
+    let hook_msg = BalanceChangedHookMsg {
+        diffs: vec![BalanceDiff {
+            address: RECIPIENT.to_string(),
+            denom: Some("uluna".to_string()),
+            old: Uint128::zero(),
+            new: Uint128::new(500),
+        }],
+    };
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(hook_msg.into_cosmos_msg("rewards").unwrap())]
+    );
+
+    let msg = ExecuteMsg::Withdraw {
+        denom: "uluna".to_string(),
+        amount: Uint128::new(200),
+        recipient: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(RECIPIENT, &[]), msg).unwrap();
+    // the hooks only hear of it once the bank send went through
+    assert_eq!(res.messages.len(), 1);
+    let success = Reply {
+        id: WITHDRAW_REPLY_ID,
+        result: ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    };
+    let res = reply(deps.as_mut(), mock_env(), success).unwrap();
+    let hook_msg = BalanceChangedHookMsg {
+        diffs: vec![BalanceDiff {
+            address: RECIPIENT.to_string(),
+            denom: Some("uluna".to_string()),
+            old: Uint128::new(500),
+            new: Uint128::new(300),
+        }],
+    };
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(hook_msg.into_cosmos_msg("rewards").unwrap())]
+    );
+}
+
+#[test]
+fn failed_withdrawals_are_not_reported_to_hooks() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(100));
+    let msg = ExecuteMsg::AddHook {
+        addr: "rewards".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+    do_deposit(deps.as_mut(), RECIPIENT, "uluna", 500);
+
+    let msg = ExecuteMsg::Withdraw {
+        denom: "uluna".to_string(),
+        amount: Uint128::new(200),
+        recipient: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(RECIPIENT, &[]), msg).unwrap();
+    let failure = Reply {
+        id: WITHDRAW_REPLY_ID,
+        result: ContractResult::Err("blocked address".to_string()),
+    };
+    let res = reply(deps.as_mut(), mock_env(), failure.clone()).unwrap();
+    assert_eq!(res.messages, vec![]);
+
+    // the pending withdrawal is gone once replied to
+    let err = reply(deps.as_mut(), mock_env(), failure).unwrap_err();
+    assert!(matches!(err, ContractError::Std(StdError::NotFound { .. })));
+}
+
+fn balance_at_height(deps: Deps, address: &str, height: u64, denom: Option<&str>) -> Uint128 {
+    let msg = QueryMsg::BalanceAtHeight {
+        address: address.to_string(),
+        height,
+        denom: denom.map(String::from),
+    };
+    let res: BalanceResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+    res.balance
+}
+
+fn total_supply_at_height(deps: Deps, height: u64) -> Uint128 {
+    let msg = QueryMsg::TotalSupplyAtHeight { height };
+    let res: TotalSupplyResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+    res.total_supply
+}
+
+#[test]
+fn balances_are_snapshotted_per_block() {
+    let mut deps = mock_dependencies(&[]);
+    let genesis = mock_env().block.height;
+    do_instantiate_with_minter(deps.as_mut(), RECIPIENT, Uint128::new(100), MINTER, None);
+
+    // several changes within the same block
+    let mut env = mock_env();
+    env.block.height = genesis + 1;
+    let msg = ExecuteMsg::Transfer {
+        recipient: "addr0002".to_string(),
+        amount: Uint128::new(30),
+    };
+    execute(deps.as_mut(), env.clone(), mock_info(RECIPIENT, &[]), msg).unwrap();
+    let msg = ExecuteMsg::Transfer {
+        recipient: "addr0002".to_string(),
+        amount: Uint128::new(20),
+    };
+    execute(deps.as_mut(), env.clone(), mock_info(RECIPIENT, &[]), msg).unwrap();
+    let msg = ExecuteMsg::Transfer {
+        recipient: RECIPIENT.to_string(),
+        amount: Uint128::new(5),
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("addr0002", &[]), msg).unwrap();
+    let msg = ExecuteMsg::Mint {
+        recipient: "addr0002".to_string(),
+        amount: Uint128::new(70),
+    };
+    execute(deps.as_mut(), env.clone(), mock_info(MINTER, &[]), msg).unwrap();
+    let msg = ExecuteMsg::Burn {
+        amount: Uint128::new(15),
+    };
+    execute(deps.as_mut(), env, mock_info(RECIPIENT, &[]), msg).unwrap();
+
+    // nothing before instantiation
+    assert_eq!(
+        balance_at_height(deps.as_ref(), RECIPIENT, genesis, None),
+        Uint128::zero()
+    );
+    assert_eq!(
+        total_supply_at_height(deps.as_ref(), genesis),
+        Uint128::zero()
+    );
+
+    // the beginning of the busy block still shows the genesis state
+    let height = genesis + 1;
+    assert_eq!(
+        balance_at_height(deps.as_ref(), RECIPIENT, height, None),
+        Uint128::new(100)
+    );
+    assert_eq!(
+        balance_at_height(deps.as_ref(), "addr0002", height, None),
+        Uint128::zero()
+    );
+    assert_eq!(
+        total_supply_at_height(deps.as_ref(), height),
+        Uint128::new(100)
+    );
+
+    // the next block sees the net result of all of them
+    let height = genesis + 2;
+    assert_eq!(
+        balance_at_height(deps.as_ref(), RECIPIENT, height, None),
+        Uint128::new(40)
+    );
+    assert_eq!(
+        balance_at_height(deps.as_ref(), "addr0002", height, None),
+        Uint128::new(115)
+    );
+    assert_eq!(
+        total_supply_at_height(deps.as_ref(), height),
+        Uint128::new(155)
+    );
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(40));
+    assert_eq!(
+        query_token_info(deps.as_ref()).unwrap().total_supply,
+        Uint128::new(155)
+    );
+}
+
+#[test]
+fn denom_balances_are_snapshotted_per_block() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), RECIPIENT, Uint128::new(100));
+    let msg = ExecuteMsg::WhiteListCoin {
+        denom: "uluna".to_string(),
+        status: true,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+
+    let mut env = mock_env();
+    let first = env.block.height + 1;
+    env.block.height = first;
+    let info = mock_info(RECIPIENT, &[Coin::new(500, "uluna")]);
+    execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Deposit {}).unwrap();
+    let msg = ExecuteMsg::Withdraw {
+        denom: "uluna".to_string(),
+        amount: Uint128::new(200),
+        recipient: None,
+    };
+    execute(deps.as_mut(), env.clone(), mock_info(RECIPIENT, &[]), msg).unwrap();
+
+    env.block.height = first + 1;
+    let msg = ExecuteMsg::Withdraw {
+        denom: "uluna".to_string(),
+        amount: Uint128::new(50),
+        recipient: None,
+    };
+    execute(deps.as_mut(), env, mock_info(RECIPIENT, &[]), msg).unwrap();
+
+    let uluna = Some("uluna");
+    assert_eq!(
+        balance_at_height(deps.as_ref(), RECIPIENT, first, uluna),
+        Uint128::zero()
+    );
+    assert_eq!(
+        balance_at_height(deps.as_ref(), RECIPIENT, first + 1, uluna),
+        Uint128::new(300)
+    );
+    assert_eq!(
+        balance_at_height(deps.as_ref(), RECIPIENT, first + 2, uluna),
+        Uint128::new(250)
+    );
+    // the wrapped token balance is tracked separately
+    assert_eq!(
+        balance_at_height(deps.as_ref(), RECIPIENT, first + 2, None),
+        Uint128::new(100)
+    );
+}
+
+#[test]
+fn token_info_supply_follows_the_ledger() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate_with_minter(deps.as_mut(), RECIPIENT, Uint128::new(100), MINTER, None);
+    assert_eq!(
+        TOKEN_INFO.load(&deps.storage).unwrap().total_supply,
+        Uint128::new(100)
+    );
+
+    let msg = ExecuteMsg::Mint {
+        recipient: RECIPIENT.to_string(),
+        amount: Uint128::new(70),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(MINTER, &[]), msg).unwrap();
+    let msg = ExecuteMsg::Burn {
+        amount: Uint128::new(20),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(RECIPIENT, &[]), msg).unwrap();
+    assert_eq!(
+        TOKEN_INFO.load(&deps.storage).unwrap().total_supply,
+        Uint128::new(150)
+    );
+}
+
+#[test]
+fn migrate_seeds_the_snapshotted_supply() {
+    // a contract from before the snapshotted supply, with it only in TOKEN_INFO
+    let mut deps = mock_dependencies(&[]);
+    let msg = BaseInstantiateMsg {
+        name: "Router Protocol".to_string(),
+        symbol: "ROUTE".to_string(),
+        decimals: 18,
+        initial_balances: vec![Cw20Coin {
+            address: RECIPIENT.to_string(),
+            amount: Uint128::new(100),
+        }],
+        mint: None,
+        marketing: None,
+    };
+    cw_instantiate(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+    set_contract_version(deps.as_mut().storage, "erc-20", "1.0.0").unwrap();
+    assert_eq!(
+        total_supply_at_height(deps.as_ref(), mock_env().block.height + 1),
+        Uint128::zero()
+    );
+
+    let msg = MigrateMsg {
+        admin: Some(INIT_ADDRESS.to_string()),
+    };
+    migrate(deps.as_mut(), mock_env(), msg).unwrap();
+    let height = mock_env().block.height + 1;
+    assert_eq!(
+        total_supply_at_height(deps.as_ref(), height),
+        Uint128::new(100)
+    );
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(100));
+    assert_eq!(
+        get_contract_version(&deps.storage).unwrap().version,
+        "1.1.0"
+    );
+    // the admin-only messages are reachable
+    let res: AdminResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Admin {}).unwrap()).unwrap();
+    assert_eq!(res.admin, Some(INIT_ADDRESS.to_string()));
+    let msg = ExecuteMsg::UpdateFlashLoanFee {
+        fee: Decimal::percent(1),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+
+    // cannot migrate twice
+    let msg = MigrateMsg { admin: None };
+    let err = migrate(deps.as_mut(), mock_env(), msg).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("Cannot upgrade from a newer version")
+    );
+}
+
+fn query_vesting(deps: Deps, env: Env, address: &str) -> VestingResponse {
+    let msg = QueryMsg::Vesting {
+        address: address.to_string(),
+    };
+    from_binary(&query(deps, env, msg).unwrap()).unwrap()
+}
+
+fn env_at(seconds: u64) -> Env {
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(seconds);
+    env
+}
+
+// team holds 1000 tokens vesting from now until 400s later, with a cliff at 100s
+fn do_instantiate_vesting(deps: DepsMut) -> VestingSchedule {
+    let start = mock_env().block.time.seconds();
+    let schedule = VestingSchedule {
+        start_time: start,
+        cliff_time: start + 100,
+        end_time: start + 400,
+        amount: Uint128::new(1000),
+    };
+    let instantiate_msg = InstantiateMsg {
+        name: "Router Protocol".to_string(),
+        symbol: "ROUTE".to_string(),
+        decimals: 18,
+        initial_balances: vec![],
+        mint: None,
+        marketing: None,
+        vesting: vec![VestingAccount {
+            address: "team".to_string(),
+            schedule: schedule.clone(),
+        }],
+    };
+    let info = mock_info(INIT_ADDRESS, &[]);
+    instantiate(deps, mock_env(), info, instantiate_msg).unwrap();
+    schedule
+}
+
+fn transfer_to_recipient(amount: u128) -> ExecuteMsg {
+    ExecuteMsg::Transfer {
+        recipient: RECIPIENT.to_string(),
+        amount: Uint128::new(amount),
+    }
+}
+
+#[test]
+fn vested_tokens_are_locked_until_the_cliff() {
+    let mut deps = mock_dependencies(&[]);
+    let schedule = do_instantiate_vesting(deps.as_mut());
+    assert_eq!(get_balance(deps.as_ref(), "team"), Uint128::new(1000));
+    assert_eq!(
+        query_token_info(deps.as_ref()).unwrap().total_supply,
+        Uint128::new(1000)
+    );
+
+    let env = env_at(schedule.cliff_time - 1);
+    let team = mock_info("team", &[]);
+    let err = execute(deps.as_mut(), env.clone(), team, transfer_to_recipient(1)).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::LockedTokens {
+            locked: Uint128::new(1000)
+        }
+    );
+    assert_eq!(
+        query_vesting(deps.as_ref(), env, "team"),
+        VestingResponse {
+            schedule: Some(schedule),
+            locked: Uint128::new(1000),
+            vested: Uint128::zero(),
+            claimable: Uint128::zero(),
+        }
+    );
+}
+
+#[test]
+fn vested_tokens_unlock_after_cliff() {
+    let mut deps = mock_dependencies(&[]);
+    let schedule = do_instantiate_vesting(deps.as_mut());
+    // the cliff releases the share since start, then it vests linearly
+    let env = env_at(schedule.start_time + 200);
+    assert_eq!(
+        query_vesting(deps.as_ref(), env.clone(), "team"),
+        VestingResponse {
+            schedule: Some(schedule),
+            locked: Uint128::new(500),
+            vested: Uint128::new(500),
+            claimable: Uint128::new(500),
+        }
+    );
+    let team = mock_info("team", &[]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        team.clone(),
+        transfer_to_recipient(300),
+    )
+    .unwrap();
+    let msg = transfer_to_recipient(201);
+    let err = execute(deps.as_mut(), env.clone(), team.clone(), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::LockedTokens {
+            locked: Uint128::new(500)
+        }
+    );
+    let msg = ExecuteMsg::Burn {
+        amount: Uint128::new(201),
+    };
+    let err = execute(deps.as_mut(), env, team, msg).unwrap_err();
+    assert!(matches!(err, ContractError::LockedTokens { .. }));
+}
+
+#[test]
+fn vested_tokens_are_liquid_after_the_end() {
+    let mut deps = mock_dependencies(&[]);
+    let schedule = do_instantiate_vesting(deps.as_mut());
+    let env = env_at(schedule.end_time);
+    let team = mock_info("team", &[]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        team,
+        transfer_to_recipient(1000),
+    )
+    .unwrap();
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(1000));
+    assert_eq!(
+        query_vesting(deps.as_ref(), env, "team").locked,
+        Uint128::zero()
+    );
+}
+
+#[test]
+fn admin_grants_vesting_from_own_balance() {
+    let mut deps = mock_dependencies(&[]);
+    do_instantiate(deps.as_mut(), INIT_ADDRESS, Uint128::new(1000));
+    let start = mock_env().block.time.seconds();
+    let schedule = VestingSchedule {
+        start_time: start,
+        cliff_time: start,
+        end_time: start + 100,
+        amount: Uint128::new(600),
+    };
+    let grant = |schedule: VestingSchedule| ExecuteMsg::GrantVesting {
+        recipient: "investor".to_string(),
+        schedule,
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("investor", &[]),
+        grant(schedule.clone()),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Admin(cw_controllers::AdminError::NotAdmin {})
+    );
+
+    let mut invalid = schedule.clone();
+    invalid.cliff_time = start + 200;
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(INIT_ADDRESS, &[]),
+        grant(invalid),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidVestingSchedule {});
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(INIT_ADDRESS, &[]),
+        grant(schedule.clone()),
+    )
+    .unwrap();
+    assert_eq!(get_balance(deps.as_ref(), INIT_ADDRESS), Uint128::new(400));
+    assert_eq!(get_balance(deps.as_ref(), "investor"), Uint128::new(600));
+
+    // a second schedule waits for the first one to vest
+    let err = execute(
+        deps.as_mut(),
+        env_at(start + 50),
+        mock_info(INIT_ADDRESS, &[]),
+        grant(schedule),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::VestingExists {
+            address: "investor".to_string()
+        }
+    );
+
+    let res = query_vesting(deps.as_ref(), env_at(start + 25), "investor");
+    assert_eq!(res.vested, Uint128::new(150));
+    assert_eq!(res.claimable, Uint128::new(150));
+}
+
+fn validator(address: &str) -> Validator {
+    Validator {
+        address: address.to_string(),
+        commission: Decimal::percent(5),
+        max_commission: Decimal::percent(20),
+        max_change_rate: Decimal::percent(1),
+    }
+}
+
+fn delegation(validator: &str, amount: u128, rewards: u128) -> FullDelegation {
+    FullDelegation {
+        delegator: Addr::unchecked(MOCK_CONTRACT_ADDR),
+        validator: validator.to_string(),
+        amount: Coin::new(amount, "ustake"),
+        can_redelegate: Coin::new(amount, "ustake"),
+        accumulated_rewards: vec![Coin::new(rewards, "ustake")],
+    }
+}
+
+// sets up the staking mode with two validators, INIT_ADDRESS holds 1000 wrapped
+// tokens minted outside of the pool
+fn do_setup_staking(mut deps: DepsMut) {
+    do_instantiate(deps.branch(), INIT_ADDRESS, Uint128::new(1000));
+    do_setup_staking_mode(deps);
+}
+
+fn do_setup_staking_mode(mut deps: DepsMut) {
+    let admin = mock_info(INIT_ADDRESS, &[]);
+    let msg = ExecuteMsg::UpdateStaking {
+        bond_denom: "ustake".to_string(),
+        unbonding_period: Duration::Time(100),
+    };
+    execute(deps.branch(), mock_env(), admin.clone(), msg).unwrap();
+    for validator in ["val1", "val2"] {
+        let msg = ExecuteMsg::AddValidator {
+            validator: validator.to_string(),
+        };
+        execute(deps.branch(), mock_env(), admin.clone(), msg).unwrap();
+    }
+    let msg = ExecuteMsg::WhiteListCoin {
+        denom: "ustake".to_string(),
+        status: true,
+    };
+    execute(deps, mock_env(), admin, msg).unwrap();
+}
+
+fn query_staking(deps: Deps) -> StakingResponse {
+    from_binary(&query(deps, mock_env(), QueryMsg::Staking {}).unwrap()).unwrap()
+}
+
+// staking mode on, with val1 and val2 known to the chain
+fn mock_staking_dependencies() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier
+        .update_staking("ustake", &[validator("val1"), validator("val2")], &[]);
+    do_setup_staking(deps.as_mut());
+    deps
+}
+
+fn update_delegations(querier: &mut MockQuerier, delegations: &[FullDelegation]) {
+    querier.update_staking(
+        "ustake",
+        &[validator("val1"), validator("val2")],
+        delegations,
+    );
+}
+
+fn do_stake(deps: DepsMut, address: &str, amount: u128) {
+    let info = mock_info(address, &[Coin::new(amount, "ustake")]);
+    execute(deps, mock_env(), info, ExecuteMsg::Deposit {}).unwrap();
+}
+
+// alice stakes 1000 that earn 60 on val1 and 40 on val2, then the rewards are collected
+fn do_stake_and_collect(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>) {
+    do_stake(deps.as_mut(), "alice", 1000);
+    let delegations = [delegation("val1", 500, 60), delegation("val2", 500, 40)];
+    update_delegations(&mut deps.querier, &delegations);
+    let info = mock_info("keeper", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::CollectRewards {},
+    )
+    .unwrap();
+    let delegations = [delegation("val1", 500, 0), delegation("val2", 500, 0)];
+    update_delegations(&mut deps.querier, &delegations);
+}
+
+#[test]
+fn staking_validators_must_be_known() {
+    let mut deps = mock_staking_dependencies();
+    let msg = ExecuteMsg::AddValidator {
+        validator: "val3".to_string(),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::UnknownValidator {
+            validator: "val3".to_string()
+        }
+    );
+}
+
+#[test]
+fn staking_deposits_are_delegated() {
+    let mut deps = mock_staking_dependencies();
+    let info = mock_info("alice", &[Coin::new(1000, "ustake")]);
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {}).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(StakingMsg::Delegate {
+                validator: "val1".to_string(),
+                amount: Coin::new(500, "ustake"),
+            }),
+            SubMsg::new(StakingMsg::Delegate {
+                validator: "val2".to_string(),
+                amount: Coin::new(500, "ustake"),
+            }),
+        ]
+    );
+    // minted as wrapped tokens, not kept in the native ledger
+    assert_eq!(get_balance(deps.as_ref(), "alice"), Uint128::new(1000));
+    assert_eq!(
+        get_denom_balance(deps.as_ref(), "alice", "ustake"),
+        Uint128::zero()
+    );
+    let staking = query_staking(deps.as_ref());
+    assert_eq!(staking.supply, Uint128::new(1000));
+    assert_eq!(
+        query_token_info(deps.as_ref()).unwrap().total_supply,
+        Uint128::new(2000)
+    );
+}
+
+#[test]
+fn collected_rewards_raise_the_exchange_rate() {
+    let mut deps = mock_staking_dependencies();
+    do_stake(deps.as_mut(), "alice", 1000);
+    let delegations = [delegation("val1", 500, 60), delegation("val2", 500, 40)];
+    update_delegations(&mut deps.querier, &delegations);
+    let info = mock_info("keeper", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::CollectRewards {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(DistributionMsg::WithdrawDelegatorReward {
+                validator: "val1".to_string(),
+            }),
+            SubMsg::new(DistributionMsg::WithdrawDelegatorReward {
+                validator: "val2".to_string(),
+            }),
+        ]
+    );
+    let staking = query_staking(deps.as_ref());
+    assert_eq!(staking.bonded, Uint128::new(1000));
+    assert_eq!(staking.rewards, Uint128::new(100));
+    assert_eq!(staking.exchange_rate, Decimal::percent(110));
+}
+
+#[test]
+fn later_deposits_mint_fewer_tokens() {
+    let mut deps = mock_staking_dependencies();
+    do_stake_and_collect(&mut deps);
+    do_stake(deps.as_mut(), "bob", 1100);
+    assert_eq!(get_balance(deps.as_ref(), "bob"), Uint128::new(1000));
+    assert_eq!(
+        query_staking(deps.as_ref()).exchange_rate,
+        Decimal::percent(110)
+    );
+}
+
+#[test]
+fn staking_withdrawals_unbond_at_the_exchange_rate() {
+    let mut deps = mock_staking_dependencies();
+    do_stake_and_collect(&mut deps);
+
+    // 550 are worth 500 tokens, the 100 of rewards go first
+    let msg = ExecuteMsg::Withdraw {
+        denom: "ustake".to_string(),
+        amount: Uint128::new(550),
+        recipient: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(StakingMsg::Undelegate {
+            validator: "val1".to_string(),
+            amount: Coin::new(450, "ustake"),
+        })]
+    );
+    assert_eq!(get_balance(deps.as_ref(), "alice"), Uint128::new(500));
+    let staking = query_staking(deps.as_ref());
+    assert_eq!(staking.bonded, Uint128::new(550));
+    assert_eq!(staking.supply, Uint128::new(500));
+    assert_eq!(staking.rewards, Uint128::zero());
+    assert_eq!(staking.exchange_rate, Decimal::percent(110));
+}
+
+#[test]
+fn unbonded_coins_are_claimed_after_the_period() {
+    let mut deps = mock_staking_dependencies();
+    do_stake_and_collect(&mut deps);
+    let msg = ExecuteMsg::Withdraw {
+        denom: "ustake".to_string(),
+        amount: Uint128::new(550),
+        recipient: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+
+    let msg = QueryMsg::Claims {
+        address: "alice".to_string(),
+    };
+    let claims: ClaimsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    let release_at = Duration::Time(100).after(&mock_env().block);
+    assert_eq!(claims.claims, vec![Claim::new(550, release_at)]);
+
+    let info = mock_info("alice", &[]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::Claim {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NothingToClaim {});
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::Claim {}).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "alice".to_string(),
+            amount: vec![Coin::new(550, "ustake")],
+        })]
+    );
+}
+
+#[test]
+fn frozen_accounts_cannot_claim_unbonded_coins() {
+    let mut deps = mock_staking_dependencies();
+    do_stake_and_collect(&mut deps);
+    let msg = ExecuteMsg::Withdraw {
+        denom: "ustake".to_string(),
+        amount: Uint128::new(550),
+        recipient: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+    let msg = ExecuteMsg::Freeze {
+        address: "alice".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    let info = mock_info("alice", &[]);
+    let err = execute(deps.as_mut(), env, info, ExecuteMsg::Claim {}).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::FrozenSender {
+            address: "alice".to_string()
+        }
+    );
+}
+
+#[test]
+fn staked_tokens_are_transferable() {
+    let mut deps = mock_staking_dependencies();
+    do_stake_and_collect(&mut deps);
+    let msg = ExecuteMsg::Transfer {
+        recipient: "bob".to_string(),
+        amount: Uint128::new(400),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::Withdraw {
+        denom: "ustake".to_string(),
+        amount: Uint128::new(440),
+        recipient: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg).unwrap();
+    assert_eq!(get_balance(deps.as_ref(), "bob"), Uint128::zero());
+    let msg = QueryMsg::Claims {
+        address: "bob".to_string(),
+    };
+    let claims: ClaimsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    let release_at = Duration::Time(100).after(&mock_env().block);
+    assert_eq!(claims.claims, vec![Claim::new(440, release_at)]);
+}
+
+#[test]
+fn unbonds_are_rounded_in_favour_of_the_pool() {
+    let mut deps = mock_staking_dependencies();
+    do_stake_and_collect(&mut deps);
+    // 100 are worth 90.9 tokens, so 91 are burned
+    let msg = ExecuteMsg::Withdraw {
+        denom: "ustake".to_string(),
+        amount: Uint128::new(100),
+        recipient: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+    assert_eq!(get_balance(deps.as_ref(), "alice"), Uint128::new(909));
+    assert_eq!(query_staking(deps.as_ref()).supply, Uint128::new(909));
+
+    let msg = ExecuteMsg::Withdraw {
+        denom: "ustake".to_string(),
+        amount: Uint128::new(1001),
+        recipient: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InsufficientStake {
+            available: Uint128::new(1000)
+        }
+    );
+}
+
+#[test]
+fn legacy_bond_denom_deposits_stay_withdrawable() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier
+        .update_staking("ustake", &[validator("val1"), validator("val2")], &[]);
+    do_instantiate(deps.as_mut(), INIT_ADDRESS, Uint128::zero());
+    do_deposit(deps.as_mut(), "alice", "ustake", 300);
+    do_setup_staking_mode(deps.as_mut());
+
+    // deposited before staking was on, so paid out right away
+    let msg = ExecuteMsg::Withdraw {
+        denom: "ustake".to_string(),
+        amount: Uint128::new(300),
+        recipient: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    let send = BankMsg::Send {
+        to_address: "alice".to_string(),
+        amount: vec![Coin::new(300, "ustake")],
+    };
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(send, WITHDRAW_REPLY_ID)]
+    );
+    assert_eq!(
+        get_denom_balance(deps.as_ref(), "alice", "ustake"),
+        Uint128::zero()
+    );
+
+    // from then on withdrawals unbond, and nothing is staked
+    let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InsufficientStake {
+            available: Uint128::zero()
+        }
+    );
+}
+
+#[test]
+fn rewards_cannot_move_during_a_flash_loan() {
+    let mut deps = mock_dependencies(&[Coin::new(1000, "ustake")]);
+    deps.querier
+        .update_staking("ustake", &[validator("val1"), validator("val2")], &[]);
+    do_setup_staking(deps.as_mut());
+    let msg = flash_loan("ustake", 1000);
+    execute(deps.as_mut(), mock_env(), mock_info("borrower", &[]), msg).unwrap();
+
+    for msg in [ExecuteMsg::CollectRewards {}, ExecuteMsg::Compound {}] {
+        let info = mock_info("borrower", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(err, ContractError::FlashLoanInProgress {});
+    }
+}
+
+fn compound_fees(keeper_tip: u64, protocol_fee: u64, fee_recipient: Option<&str>) -> ExecuteMsg {
+    ExecuteMsg::UpdateCompoundFees {
+        keeper_tip: Decimal::percent(keeper_tip),
+        protocol_fee: Decimal::percent(protocol_fee),
+        fee_recipient: fee_recipient.map(String::from),
+    }
+}
+
+#[test]
+fn compound_fees_are_validated() {
+    let mut deps = mock_staking_dependencies();
+    let admin = mock_info(INIT_ADDRESS, &[]);
+    let msg = compound_fees(60, 50, Some("treasury"));
+    let err = execute(deps.as_mut(), mock_env(), admin.clone(), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidFee {});
+    let msg = compound_fees(5, 10, None);
+    let err = execute(deps.as_mut(), mock_env(), admin, msg).unwrap_err();
+    assert_eq!(err, ContractError::NoFeeRecipient {});
+}
+
+#[test]
+fn compound_pays_keeper_and_protocol_then_restakes() {
+    let mut deps = mock_staking_dependencies();
+    do_stake(deps.as_mut(), "alice", 900);
+    let msg = compound_fees(5, 10, Some("treasury"));
+    execute(deps.as_mut(), mock_env(), mock_info(INIT_ADDRESS, &[]), msg).unwrap();
+
+    let delegations = [delegation("val1", 450, 60), delegation("val2", 450, 40)];
+    update_delegations(&mut deps.querier, &delegations);
+    let info = mock_info("keeper", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Compound {}).unwrap();
+    let messages: Vec<SubMsg> = vec![
+        SubMsg::new(DistributionMsg::WithdrawDelegatorReward {
+            validator: "val1".to_string(),
+        }),
+        SubMsg::new(DistributionMsg::WithdrawDelegatorReward {
+            validator: "val2".to_string(),
+        }),
+        SubMsg::new(BankMsg::Send {
+            to_address: "keeper".to_string(),
+            amount: vec![Coin::new(5, "ustake")],
+        }),
+        SubMsg::new(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: vec![Coin::new(10, "ustake")],
+        }),
+        // 85 restaked pro rata, the rounding remainder to the largest delegation
+        SubMsg::new(StakingMsg::Delegate {
+            validator: "val1".to_string(),
+            amount: Coin::new(43, "ustake"),
+        }),
+        SubMsg::new(StakingMsg::Delegate {
+            validator: "val2".to_string(),
+            amount: Coin::new(42, "ustake"),
+        }),
+    ];
+    assert_eq!(res.messages, messages);
+
+    let staking = query_staking(deps.as_ref());
+    assert_eq!(staking.bonded, Uint128::new(985));
+    assert_eq!(staking.rewards, Uint128::zero());
+    assert_eq!(staking.exchange_rate, Decimal::from_ratio(985u128, 900u128));
+    assert_eq!(get_balance(deps.as_ref(), "keeper"), Uint128::zero());
+}
+
+fn query_delegations(deps: Deps) -> Vec<DelegationInfo> {
+    let res: DelegationsResponse =
+        from_binary(&query(deps, mock_env(), QueryMsg::Delegations {}).unwrap()).unwrap();
+    res.delegations
+}
+
+fn validator_weights(val1: u64, val2: u64) -> ExecuteMsg {
+    ExecuteMsg::SetValidatorWeights {
+        weights: vec![
+            ValidatorWeight {
+                validator: "val1".to_string(),
+                weight: val1,
+            },
+            ValidatorWeight {
+                validator: "val2".to_string(),
+                weight: val2,
+            },
+        ],
+    }
+}
+
+fn delegation_info(validator: &str, weight: u64, current: u128, target: u128) -> DelegationInfo {
+    DelegationInfo {
+        validator: validator.to_string(),
+        weight,
+        current: Uint128::new(current),
+        target: Uint128::new(target),
+    }
+}
+
+// alice stakes 1000, split evenly, toward a 3:1 target
+fn do_set_validator_weights(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>) {
+    do_stake(deps.as_mut(), "alice", 1000);
+    let admin = mock_info(INIT_ADDRESS, &[]);
+    execute(deps.as_mut(), mock_env(), admin, validator_weights(3, 1)).unwrap();
+    let delegations = [delegation("val1", 500, 0), delegation("val2", 500, 0)];
+    update_delegations(&mut deps.querier, &delegations);
+}
+
+#[test]
+fn validator_weights_set_delegation_targets() {
+    let mut deps = mock_staking_dependencies();
+    do_stake(deps.as_mut(), "alice", 1000);
+    let admin = mock_info(INIT_ADDRESS, &[]);
+    let msg = validator_weights(0, 0);
+    let err = execute(deps.as_mut(), mock_env(), admin.clone(), msg).unwrap_err();
+    assert_eq!(err, ContractError::NoValidators {});
+    execute(deps.as_mut(), mock_env(), admin, validator_weights(3, 1)).unwrap();
+    assert_eq!(
+        query_delegations(deps.as_ref()),
+        vec![
+            delegation_info("val1", 3, 500, 750),
+            delegation_info("val2", 1, 500, 250)
+        ]
+    );
+}
+
+#[test]
+fn rebalance_moves_stake_toward_weights() {
+    let mut deps = mock_staking_dependencies();
+    do_set_validator_weights(&mut deps);
+    let info = mock_info("alice", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Rebalance {}).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Admin(cw_controllers::AdminError::NotAdmin {})
+    );
+
+    let admin = mock_info(INIT_ADDRESS, &[]);
+    let res = execute(deps.as_mut(), mock_env(), admin, ExecuteMsg::Rebalance {}).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(StakingMsg::Redelegate {
+            src_validator: "val2".to_string(),
+            dst_validator: "val1".to_string(),
+            amount: Coin::new(250, "ustake"),
+        })]
+    );
+    assert_eq!(
+        query_delegations(deps.as_ref()),
+        vec![
+            delegation_info("val1", 3, 750, 750),
+            delegation_info("val2", 1, 250, 250)
+        ]
+    );
+    // the exchange rate is untouched
+    assert_eq!(query_staking(deps.as_ref()).bonded, Uint128::new(1000));
+}
+
+#[test]
+fn rebalance_moves_only_what_can_be_redelegated() {
+    let mut deps = mock_staking_dependencies();
+    do_set_validator_weights(&mut deps);
+    // only 100 of val2 can be redelegated right away
+    let mut limited = delegation("val2", 500, 0);
+    limited.can_redelegate = Coin::new(100, "ustake");
+    update_delegations(&mut deps.querier, &[delegation("val1", 500, 0), limited]);
+
+    let admin = mock_info(INIT_ADDRESS, &[]);
+    let res = execute(deps.as_mut(), mock_env(), admin, ExecuteMsg::Rebalance {}).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(StakingMsg::Redelegate {
+            src_validator: "val2".to_string(),
+            dst_validator: "val1".to_string(),
+            amount: Coin::new(100, "ustake"),
+        })]
+    );
+    assert_eq!(
+        query_delegations(deps.as_ref()),
+        vec![
+            delegation_info("val1", 3, 600, 750),
+            delegation_info("val2", 1, 400, 250)
+        ]
+    );
+}
+
+const ICS20_CHANNEL: &str = "channel-9";
+
+// instantiates with 1000 tokens for RECIPIENT and connects an ICS-20 channel
+fn do_connect_channel(deps: DepsMut) {
+    let mut deps = deps;
+    do_instantiate(deps.branch(), RECIPIENT, Uint128::new(1000));
+    let msg = mock_ibc_channel_open_try(ICS20_CHANNEL, IbcOrder::Unordered, ICS20_VERSION);
+    ibc_channel_open(deps.branch(), mock_env(), msg).unwrap();
+    let msg = mock_ibc_channel_connect_ack(ICS20_CHANNEL, IbcOrder::Unordered, ICS20_VERSION);
+    ibc_channel_connect(deps, mock_env(), msg).unwrap();
+}
+
+fn ibc_transfer_packet(amount: u128) -> Ics20Packet {
+    Ics20Packet {
+        denom: format!("cw20:{}", MOCK_CONTRACT_ADDR),
+        amount: Uint128::new(amount),
+        sender: RECIPIENT.to_string(),
+        receiver: "remote1".to_string(),
+    }
+}
+
+fn channel_balance(deps: Deps) -> Uint128 {
+    let msg = QueryMsg::Channel {
+        id: ICS20_CHANNEL.to_string(),
+    };
+    let res: ChannelResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+    res.balance
+}
+
+#[test]
+fn ibc_channels_must_be_unordered_ics20() {
+    let mut deps = mock_dependencies(&[]);
+    let msg = mock_ibc_channel_open_init(ICS20_CHANNEL, IbcOrder::Ordered, ICS20_VERSION);
+    let err = ibc_channel_open(deps.as_mut(), mock_env(), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidIbcOrder {});
+    let msg = mock_ibc_channel_open_try(ICS20_CHANNEL, IbcOrder::Unordered, "ics20-2");
+    let err = ibc_channel_open(deps.as_mut(), mock_env(), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidIbcVersion {
+            version: "ics20-2".to_string()
+        }
+    );
+
+    do_connect_channel(deps.as_mut());
+    let res: ListChannelsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::ListChannels {}).unwrap()).unwrap();
+    assert_eq!(res.channels.len(), 1);
+    assert_eq!(res.channels[0].id, ICS20_CHANNEL);
+    assert_eq!(
+        res.channels[0].counterparty_endpoint.channel_id,
+        "channel-7"
+    );
+}
+
+// RECIPIENT sends amount over the channel
+fn do_ibc_transfer(deps: DepsMut, amount: u128) {
+    let msg = ExecuteMsg::IbcTransfer {
+        channel: ICS20_CHANNEL.to_string(),
+        remote_address: "remote1".to_string(),
+        amount: Uint128::new(amount),
+        timeout: None,
+    };
+    execute(deps, mock_env(), mock_info(RECIPIENT, &[]), msg).unwrap();
+}
+
+fn ibc_ack(ack: Ics20Ack, amount: u128) -> IbcPacketAckMsg {
+    let ack = IbcAcknowledgement::encode_json(&ack).unwrap();
+    mock_ibc_packet_ack(ICS20_CHANNEL, &ibc_transfer_packet(amount), ack).unwrap()
+}
+
+#[test]
+fn ibc_transfer_escrows_in_the_channel() {
+    let mut deps = mock_dependencies(&[]);
+    do_connect_channel(deps.as_mut());
+    let transfer = ExecuteMsg::IbcTransfer {
+        channel: ICS20_CHANNEL.to_string(),
+        remote_address: "remote1".to_string(),
+        amount: Uint128::new(300),
+        timeout: None,
+    };
+    let info = mock_info(RECIPIENT, &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, transfer).unwrap();
+    let timeout = mock_env().block.time.plus_seconds(DEFAULT_IBC_TIMEOUT);
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(IbcMsg::SendPacket {
+            channel_id: ICS20_CHANNEL.to_string(),
+            data: to_binary(&ibc_transfer_packet(300)).unwrap(),
+            timeout: IbcTimeout::with_timestamp(timeout),
+        })]
+    );
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(700));
+    // held by the channel escrow, not the contract
+    assert_eq!(
+        get_balance(deps.as_ref(), MOCK_CONTRACT_ADDR),
+        Uint128::zero()
+    );
+    assert_eq!(channel_balance(deps.as_ref()), Uint128::new(300));
+}
+
+#[test]
+fn ibc_timeout_refunds_the_sender() {
+    let mut deps = mock_dependencies(&[]);
+    do_connect_channel(deps.as_mut());
+    do_ibc_transfer(deps.as_mut(), 300);
+    let msg = mock_ibc_packet_timeout(ICS20_CHANNEL, &ibc_transfer_packet(300)).unwrap();
+    ibc_packet_timeout(deps.as_mut(), mock_env(), msg).unwrap();
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(1000));
+    assert_eq!(channel_balance(deps.as_ref()), Uint128::zero());
+}
+
+#[test]
+fn ibc_error_ack_refunds_the_sender() {
+    let mut deps = mock_dependencies(&[]);
+    do_connect_channel(deps.as_mut());
+    do_ibc_transfer(deps.as_mut(), 300);
+    let msg = ibc_ack(Ics20Ack::Error("bad".to_string()), 300);
+    ibc_packet_ack(deps.as_mut(), mock_env(), msg).unwrap();
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(1000));
+    assert_eq!(channel_balance(deps.as_ref()), Uint128::zero());
+}
+
+#[test]
+fn ibc_success_ack_keeps_the_escrow() {
+    let mut deps = mock_dependencies(&[]);
+    do_connect_channel(deps.as_mut());
+    do_ibc_transfer(deps.as_mut(), 300);
+    let msg = ibc_ack(Ics20Ack::Result(Binary::from(vec![1])), 300);
+    ibc_packet_ack(deps.as_mut(), mock_env(), msg).unwrap();
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(700));
+    assert_eq!(channel_balance(deps.as_ref()), Uint128::new(300));
+}
+
+// the mock packet comes from their-port/channel-1234
+fn returning_packet(amount: u128, denom: &str) -> Ics20Packet {
+    Ics20Packet {
+        denom: denom.to_string(),
+        amount: Uint128::new(amount),
+        sender: "remote1".to_string(),
+        receiver: "local2".to_string(),
+    }
+}
+
+fn voucher_denom() -> String {
+    format!("their-port/channel-1234/cw20:{}", MOCK_CONTRACT_ADDR)
+}
+
+#[test]
+fn ibc_receive_accepts_only_the_wrapped_token() {
+    let mut deps = mock_dependencies(&[]);
+    do_connect_channel(deps.as_mut());
+    do_ibc_transfer(deps.as_mut(), 300);
+    let msg = mock_ibc_packet_recv(ICS20_CHANNEL, &returning_packet(100, "uatom")).unwrap();
+    let res = ibc_packet_receive(deps.as_mut(), mock_env(), msg).unwrap();
+    let ack: Ics20Ack = from_binary(&res.acknowledgement).unwrap();
+    assert_eq!(
+        ack,
+        Ics20Ack::Error("Only the wrapped token can be received, got uatom".to_string())
+    );
+}
+
+#[test]
+fn ibc_receive_cannot_exceed_the_channel_escrow() {
+    let mut deps = mock_dependencies(&[]);
+    do_connect_channel(deps.as_mut());
+    do_ibc_transfer(deps.as_mut(), 300);
+    let packet = returning_packet(301, &voucher_denom());
+    let msg = mock_ibc_packet_recv(ICS20_CHANNEL, &packet).unwrap();
+    let res = ibc_packet_receive(deps.as_mut(), mock_env(), msg).unwrap();
+    let ack: Ics20Ack = from_binary(&res.acknowledgement).unwrap();
+    assert_eq!(
+        ack,
+        Ics20Ack::Error(format!("Only 300 was sent over channel {}", ICS20_CHANNEL))
+    );
+    assert_eq!(get_balance(deps.as_ref(), "local2"), Uint128::zero());
+}
+
+#[test]
+fn ibc_receive_releases_escrowed_tokens() {
+    let mut deps = mock_dependencies(&[]);
+    do_connect_channel(deps.as_mut());
+    do_ibc_transfer(deps.as_mut(), 300);
+    let packet = returning_packet(120, &voucher_denom());
+    let msg = mock_ibc_packet_recv(ICS20_CHANNEL, &packet).unwrap();
+    let res = ibc_packet_receive(deps.as_mut(), mock_env(), msg).unwrap();
+    let ack: Ics20Ack = from_binary(&res.acknowledgement).unwrap();
+    assert_eq!(ack, Ics20Ack::Result(Binary::from(vec![1])));
+    assert_eq!(get_balance(deps.as_ref(), "local2"), Uint128::new(120));
+    assert_eq!(channel_balance(deps.as_ref()), Uint128::new(180));
+}
+
+#[test]
+fn ibc_success_ack_after_the_tokens_returned() {
+    let mut deps = mock_dependencies(&[]);
+    do_connect_channel(deps.as_mut());
+    do_ibc_transfer(deps.as_mut(), 300);
+    let packet = returning_packet(300, &voucher_denom());
+    let msg = mock_ibc_packet_recv(ICS20_CHANNEL, &packet).unwrap();
+    ibc_packet_receive(deps.as_mut(), mock_env(), msg).unwrap();
+    assert_eq!(channel_balance(deps.as_ref()), Uint128::zero());
+
+    let msg = ibc_ack(Ics20Ack::Result(Binary::from(vec![1])), 300);
+    ibc_packet_ack(deps.as_mut(), mock_env(), msg).unwrap();
+    assert_eq!(get_balance(deps.as_ref(), "local2"), Uint128::new(300));
+    assert_eq!(channel_balance(deps.as_ref()), Uint128::zero());
+}
+
+#[test]
+fn ibc_refunds_cannot_exceed_the_channel_escrow() {
+    let mut deps = mock_dependencies(&[]);
+    do_connect_channel(deps.as_mut());
+    // tokens of an escrow sit in the contract balance
+    let msg = ExecuteMsg::CreateEscrow {
+        recipient: "bob".to_string(),
+        arbiter: "arbiter".to_string(),
+        amount: Uint128::new(500),
+        expiration: Expiration::Never {},
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(RECIPIENT, &[]), msg).unwrap();
+    do_ibc_transfer(deps.as_mut(), 300);
+
+    let msg = mock_ibc_packet_timeout(ICS20_CHANNEL, &ibc_transfer_packet(400)).unwrap();
+    let err = ibc_packet_timeout(deps.as_mut(), mock_env(), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InsufficientChannelBalance {
+            channel: ICS20_CHANNEL.to_string(),
+            available: Uint128::new(300),
+        }
+    );
+
+    assert_eq!(get_balance(deps.as_ref(), RECIPIENT), Uint128::new(200));
+    assert_eq!(
+        get_balance(deps.as_ref(), MOCK_CONTRACT_ADDR),
+        Uint128::new(500)
+    );
+}
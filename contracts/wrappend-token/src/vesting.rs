@@ -0,0 +1,158 @@
+use cosmwasm_std::{
+    Addr, BlockInfo, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage, Uint128,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::compliance::assert_recipient_not_frozen;
+use crate::erc20::VestingResponse;
+use crate::error::ContractError;
+use crate::hooks::{add_balance_hooks, TokenBalances};
+use crate::ledger::{move_token_balance, token_balance};
+use crate::state::{ADMIN, VESTING};
+
+/// Cliff plus linear release of amount tokens. Nothing is vested before the
+/// cliff, then the tokens vest linearly from start to end, so the share for
+/// start..cliff is released at once when the cliff is reached. Times are in
+/// seconds since the epoch.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VestingSchedule {
+    pub start_time: u64,
+    pub cliff_time: u64,
+    pub end_time: u64,
+    pub amount: Uint128,
+}
+
+impl VestingSchedule {
+    pub fn validate(&self) -> Result<(), ContractError> {
+        if self.amount.is_zero()
+            || self.start_time > self.cliff_time
+            || self.cliff_time > self.end_time
+            || self.start_time == self.end_time
+        {
+            return Err(ContractError::InvalidVestingSchedule {});
+        }
+        Ok(())
+    }
+
+    pub fn vested(&self, block: &BlockInfo) -> Uint128 {
+        let now = block.time.seconds();
+        if now < self.cliff_time {
+            Uint128::zero()
+        } else if now >= self.end_time {
+            self.amount
+        } else {
+            self.amount
+                .multiply_ratio(now - self.start_time, self.end_time - self.start_time)
+        }
+    }
+
+    pub fn locked(&self, block: &BlockInfo) -> Uint128 {
+        self.amount - self.vested(block)
+    }
+}
+
+/// Part of the wrapped token balance of the account that cannot be moved yet
+pub fn locked_amount(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    address: &Addr,
+) -> StdResult<Uint128> {
+    Ok(VESTING
+        .may_load(storage, address)?
+        .map(|schedule| schedule.locked(block))
+        .unwrap_or_default())
+}
+
+/// Called before a debit, fails if the balance left would not cover the locked
+/// tokens. Overdrafts are left to the ledger to report.
+pub fn assert_vested(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    address: &Addr,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    let locked = locked_amount(storage, block, address)?;
+    let balance = token_balance(storage, address)?;
+    if balance >= amount && balance - amount < locked {
+        return Err(ContractError::LockedTokens { locked });
+    }
+    Ok(())
+}
+
+/// Attaches the schedule to the account. A previous schedule can only be
+/// replaced once it has fully vested.
+pub fn save_vesting(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    address: &Addr,
+    schedule: &VestingSchedule,
+) -> Result<(), ContractError> {
+    schedule.validate()?;
+    if let Some(existing) = VESTING.may_load(storage, address)? {
+        if !existing.locked(block).is_zero() {
+            return Err(ContractError::VestingExists {
+                address: address.to_string(),
+            });
+        }
+    }
+    VESTING.save(storage, address, schedule)?;
+    Ok(())
+}
+
+/// Moves the allocation out of the admin balance to the recipient, locked
+/// under the schedule
+pub fn execute_grant_vesting(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: String,
+    schedule: VestingSchedule,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let rcpt_addr = deps.api.addr_validate(&recipient)?;
+    if rcpt_addr == info.sender {
+        return Err(ContractError::CannotSetOwnAccount {});
+    }
+    assert_recipient_not_frozen(deps.storage, &rcpt_addr)?;
+    save_vesting(deps.storage, &env.block, &rcpt_addr, &schedule)?;
+
+    assert_vested(deps.storage, &env.block, &info.sender, schedule.amount)?;
+    let before = TokenBalances::load(deps.storage, &[&info.sender, &rcpt_addr])?;
+    move_token_balance(
+        deps.storage,
+        env.block.height,
+        &info.sender,
+        &rcpt_addr,
+        schedule.amount,
+    )?;
+
+    let res = Response::new()
+        .add_attribute("action", "grant_vesting")
+        .add_attribute("from", info.sender)
+        .add_attribute("to", recipient)
+        .add_attribute("amount", schedule.amount)
+        .add_attribute("cliff_time", schedule.cliff_time.to_string())
+        .add_attribute("end_time", schedule.end_time.to_string());
+    Ok(add_balance_hooks(
+        deps.storage,
+        res,
+        before.diffs(deps.storage)?,
+    )?)
+}
+
+pub fn query_vesting(deps: Deps, env: Env, address: String) -> StdResult<VestingResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let schedule = VESTING.may_load(deps.storage, &address)?;
+    let (locked, vested) = match &schedule {
+        Some(schedule) => (schedule.locked(&env.block), schedule.vested(&env.block)),
+        None => (Uint128::zero(), Uint128::zero()),
+    };
+    let claimable = token_balance(deps.storage, &address)?.saturating_sub(locked);
+    Ok(VestingResponse {
+        schedule,
+        locked,
+        vested,
+        claimable,
+    })
+}
@@ -0,0 +1,142 @@
+use cosmwasm_std::{
+    Addr, BankMsg, Coin, ContractResult, Deps, DepsMut, Env, Order, Reply, Response, StdResult,
+    Storage, SubMsg, Uint128,
+};
+use cw_storage_plus::{Bound, U64Key};
+
+use crate::erc20::FailedWithdrawalsResponse;
+use crate::error::ContractError;
+use crate::hooks::{add_balance_hooks, BalanceDiff};
+use crate::ledger::{denom_balance, increase_denom_balance};
+use crate::state::{
+    FailedWithdrawal, PendingWithdrawal, FAILED_WITHDRAWALS, FAILED_WITHDRAWAL_SEQ,
+    PENDING_WITHDRAWAL,
+};
+
+pub const WITHDRAW_REPLY_ID: u64 = 1;
+
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+/// Bank send of a withdrawal already taken from the owner's balance. Its outcome
+/// comes back to `reply_withdraw`, and a failed send does not revert the
+/// transaction.
+pub fn withdrawal_msg(
+    storage: &mut dyn Storage,
+    owner: &Addr,
+    recipient: &Addr,
+    denom: &str,
+    amount: Uint128,
+) -> StdResult<SubMsg> {
+    // only one withdrawal is sent per execution, so the last one is the one replying
+    PENDING_WITHDRAWAL.save(
+        storage,
+        &PendingWithdrawal {
+            owner: owner.clone(),
+            recipient: recipient.clone(),
+            denom: denom.to_string(),
+            amount,
+        },
+    )?;
+    let send = BankMsg::Send {
+        to_address: recipient.to_string(),
+        amount: vec![Coin {
+            denom: denom.to_string(),
+            amount,
+        }],
+    };
+    Ok(SubMsg::reply_always(send, WITHDRAW_REPLY_ID))
+}
+
+/// Reports the debit of a sent withdrawal to the hooks. A failed one is put back
+/// in the owner's balance, so the hooks see no change, and queued so the owner
+/// can retry it.
+pub fn reply_withdraw(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let pending = PENDING_WITHDRAWAL.load(deps.storage)?;
+    PENDING_WITHDRAWAL.remove(deps.storage);
+    let error = match msg.result {
+        ContractResult::Err(error) => error,
+        ContractResult::Ok(_) => {
+            let new = denom_balance(deps.storage, &pending.owner, &pending.denom)?;
+            let diff = BalanceDiff {
+                address: pending.owner.to_string(),
+                denom: Some(pending.denom),
+                old: new + pending.amount,
+                new,
+            };
+            return Ok(add_balance_hooks(
+                deps.storage,
+                Response::new(),
+                vec![diff],
+            )?);
+        }
+    };
+
+    increase_denom_balance(
+        deps.storage,
+        env.block.height,
+        &pending.owner,
+        &pending.denom,
+        pending.amount,
+    )?;
+
+    let id = FAILED_WITHDRAWAL_SEQ
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        + 1;
+    FAILED_WITHDRAWAL_SEQ.save(deps.storage, &id)?;
+    let failed = FailedWithdrawal {
+        id,
+        recipient: pending.recipient,
+        denom: pending.denom,
+        amount: pending.amount,
+        error,
+    };
+    FAILED_WITHDRAWALS.save(deps.storage, (&pending.owner, U64Key::new(id)), &failed)?;
+
+    let res = Response::new()
+        .add_attribute("action", "withdraw_failed")
+        .add_attribute("id", id.to_string())
+        .add_attribute("owner", pending.owner)
+        .add_attribute("to", failed.recipient)
+        .add_attribute("denom", failed.denom)
+        .add_attribute("amount", failed.amount)
+        .add_attribute("error", failed.error);
+    Ok(res)
+}
+
+/// Takes a failed withdrawal of the owner off the queue to send it again
+pub fn take_failed_withdrawal(
+    storage: &mut dyn Storage,
+    owner: &Addr,
+    id: u64,
+) -> Result<FailedWithdrawal, ContractError> {
+    let key = (owner, U64Key::new(id));
+    let failed = FAILED_WITHDRAWALS
+        .may_load(storage, key.clone())?
+        .ok_or(ContractError::UnknownFailedWithdrawal { id })?;
+    FAILED_WITHDRAWALS.remove(storage, key);
+    Ok(failed)
+}
+
+pub fn query_failed_withdrawals(
+    deps: Deps,
+    address: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<FailedWithdrawalsResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+
+    let withdrawals: StdResult<Vec<_>> = FAILED_WITHDRAWALS
+        .prefix(&address)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, failed)| failed))
+        .collect();
+
+    Ok(FailedWithdrawalsResponse {
+        withdrawals: withdrawals?,
+    })
+}